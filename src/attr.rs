@@ -0,0 +1,76 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-values-5/#attr-notation
+
+use tokenizer::Token;
+use {BasicParseError, CowRcStr, ParseError, Parser};
+
+/// A parsed `attr()` function, such as `attr(data-count number, 0)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttrFunction<'i> {
+    /// The name of the attribute.
+    pub name: CowRcStr<'i>,
+
+    /// The type or unit keyword following the name, if any.
+    /// `%` is represented as the string `"%"`.
+    pub type_or_unit: Option<CowRcStr<'i>>,
+
+    /// The CSS source of the fallback value after the comma, if any,
+    /// without surrounding whitespace.
+    pub fallback: Option<&'i str>,
+}
+
+impl<'i> AttrFunction<'i> {
+    /// Parse an `attr()` function, starting at its function token.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        input.expect_function_matching("attr")?;
+        input
+            .parse_nested_block(|input| parse_arguments(input).map_err(ParseError::from))
+            .map_err(ParseError::<()>::basic)
+    }
+}
+
+fn parse_arguments<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<AttrFunction<'i>, BasicParseError<'i>> {
+    let name = input.expect_ident_cloned()?;
+    let type_or_unit = match input.next() {
+        Err(_) => None,
+        Ok(&Token::Comma) => return parse_fallback(input, name, None),
+        Ok(Token::Ident(value)) => Some(value.clone()),
+        Ok(&Token::Delim('%')) => Some("%".into()),
+        Ok(t) => {
+            let t = t.clone();
+            return Err(input.new_basic_unexpected_token_error(t));
+        }
+    };
+    match input.next() {
+        Err(_) => Ok(AttrFunction {
+            name,
+            type_or_unit,
+            fallback: None,
+        }),
+        Ok(&Token::Comma) => parse_fallback(input, name, type_or_unit),
+        Ok(t) => {
+            let t = t.clone();
+            Err(input.new_basic_unexpected_token_error(t))
+        }
+    }
+}
+
+fn parse_fallback<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    name: CowRcStr<'i>,
+    type_or_unit: Option<CowRcStr<'i>>,
+) -> Result<AttrFunction<'i>, BasicParseError<'i>> {
+    input.skip_whitespace();
+    let start = input.position();
+    while input.next().is_ok() {}
+    Ok(AttrFunction {
+        name,
+        type_or_unit,
+        fallback: Some(input.slice_from(start).trim_end()),
+    })
+}
@@ -527,6 +527,50 @@ pub fn parse_color_keyword(ident: &str) -> Result<Color, ()> {
     keyword(ident).cloned().ok_or(())
 }
 
+/// Normalize a color hash to lowercase hex digits.
+/// Both the hash and the result are without the leading '#' character.
+///
+/// The 3 and 4 digit forms are expanded to 6 and 8 digits.
+/// If `minify` is true, a 6 or 8 digit form is then shortened to 3 or 4 digits
+/// when every channel repeats its digit, as in `ff0000` to `f00`.
+/// Return `None` if the hash is not a valid hex color.
+pub fn normalize_hex_color(hash: &str, minify: bool) -> Option<String> {
+    if !matches!(hash.len(), 3 | 4 | 6 | 8) || hash.bytes().any(|b| from_hex(b).is_err()) {
+        return None;
+    }
+    let hash = hash.to_ascii_lowercase();
+    let mut long = String::with_capacity(8);
+    if hash.len() <= 4 {
+        for c in hash.chars() {
+            long.push(c);
+            long.push(c);
+        }
+    } else {
+        long = hash
+    }
+    let pairs = long.as_bytes().chunks(2);
+    if minify && pairs.clone().all(|pair| pair[0] == pair[1]) {
+        return Some(pairs.map(|pair| pair[0] as char).collect());
+    }
+    Some(long)
+}
+
+/// Return the red, green, blue and alpha bytes of a `Hash` or `IDHash` token
+/// whose value is 3, 4, 6 or 8 hex digits.
+///
+/// The 3 and 4 digit forms repeat each digit, and the 3 and 6 digit forms are opaque.
+/// Return `None` for any other token.
+pub fn parse_color_hash(token: &Token) -> Option<(u8, u8, u8, u8)> {
+    let value = match *token {
+        Token::Hash(ref value) | Token::IDHash(ref value) => value,
+        _ => return None,
+    };
+    match Color::parse_hash(value.as_bytes()) {
+        Ok(Color::RGBA(rgba)) => Some((rgba.red, rgba.green, rgba.blue, rgba.alpha)),
+        _ => None,
+    }
+}
+
 #[inline]
 fn from_hex(c: u8) -> Result<u8, ()> {
     match c {
@@ -0,0 +1,129 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-syntax/#component-value
+
+use tokenizer::Token;
+use {BasicParseError, BasicParseErrorKind, BlockType, CowRcStr, ParseError, Parser};
+
+/// A component value: a token, or a function or simple block with its contents.
+///
+/// Unlike the flat token stream, functions and blocks own the component values
+/// up to their matching closing token, which is not included.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComponentValue<'i> {
+    /// Any token other than `Function`, `ParenthesisBlock`,
+    /// `SquareBracketBlock` or `CurlyBracketBlock`.
+    ///
+    /// This includes whitespace, and closing tokens that are unmatched
+    /// (and therefore an error).
+    Token(Token<'i>),
+
+    /// A `(…)`, `[…]` or `{…}` block.
+    Block {
+        /// Which brackets delimit the block.
+        kind: BlockType,
+        /// The contents of the block.
+        contents: Vec<ComponentValue<'i>>,
+    },
+
+    /// A function, such as `rgb(…)`.
+    Function {
+        /// The name of the function, unescaped.
+        name: CowRcStr<'i>,
+        /// The contents of the function, including commas.
+        arguments: Vec<ComponentValue<'i>>,
+    },
+}
+
+/// How deeply functions and blocks can be nested in `ComponentValue::parse`
+/// and `ComponentValue::parse_list`.
+const DEFAULT_MAX_DEPTH: usize = 512;
+
+impl<'i> ComponentValue<'i> {
+    /// Parse the next component value, including all of a function or block.
+    ///
+    /// Comments are skipped, whitespace is not.
+    /// A function or block that is not closed before the end of the input
+    /// is closed implicitly, as in the specification.
+    ///
+    /// Functions and blocks nested more than 512 levels deep are an error,
+    /// see `parse_with_max_depth`.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        ComponentValue::parse_with_max_depth(input, DEFAULT_MAX_DEPTH)
+    }
+
+    /// Parse the next component value, like `parse`,
+    /// with at most `max_depth` levels of functions and blocks nested in each other.
+    ///
+    /// A function or block nested deeper is an unexpected token error,
+    /// and the value that contains it is consumed.
+    /// This bounds the recursion on input such as thousands of `(`.
+    pub fn parse_with_max_depth<'t>(
+        input: &mut Parser<'i, 't>,
+        max_depth: usize,
+    ) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let token = input.next_including_whitespace()?.clone();
+        let kind = match token.block_start() {
+            Some(kind) => kind,
+            None => return Ok(ComponentValue::Token(token)),
+        };
+        if max_depth == 0 {
+            return Err(location.new_basic_unexpected_token_error(token));
+        }
+        let contents = input
+            .parse_nested_block(|input| {
+                ComponentValue::parse_list_with_max_depth(input, max_depth - 1)
+                    .map_err(ParseError::from)
+            })
+            .map_err(ParseError::<()>::basic)?;
+        Ok(match token {
+            Token::Function(name) => ComponentValue::Function {
+                name,
+                arguments: contents,
+            },
+            _ => ComponentValue::Block { kind, contents },
+        })
+    }
+
+    /// Parse component values until the end of the input (or of the current block).
+    ///
+    /// A value with functions or blocks nested more than 512 levels deep is skipped,
+    /// see `parse_list_with_max_depth`.
+    pub fn parse_list<'t>(input: &mut Parser<'i, 't>) -> Vec<Self> {
+        let mut values = Vec::new();
+        loop {
+            match ComponentValue::parse(input) {
+                Ok(value) => values.push(value),
+                Err(BasicParseError {
+                    kind: BasicParseErrorKind::EndOfInput,
+                    ..
+                }) => return values,
+                Err(_) => {}
+            }
+        }
+    }
+
+    /// Parse component values until the end of the input (or of the current block),
+    /// with at most `max_depth` levels of functions and blocks nested in each other.
+    ///
+    /// Return an error for the first function or block nested deeper.
+    pub fn parse_list_with_max_depth<'t>(
+        input: &mut Parser<'i, 't>,
+        max_depth: usize,
+    ) -> Result<Vec<Self>, BasicParseError<'i>> {
+        let mut values = Vec::new();
+        loop {
+            match ComponentValue::parse_with_max_depth(input, max_depth) {
+                Ok(value) => values.push(value),
+                Err(BasicParseError {
+                    kind: BasicParseErrorKind::EndOfInput,
+                    ..
+                }) => return Ok(values),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-fonts/#src-desc
+
+use {BasicParseError, CowRcStr, ParseError, Parser};
+
+/// One entry of the `src` descriptor of an `@font-face` rule.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FontSource<'i> {
+    /// `url(…)`, optionally followed by `format(…)` and `tech(…)`.
+    Url {
+        /// The URL.
+        url: CowRcStr<'i>,
+        /// The argument of `format()`, if any: a string or a keyword.
+        format: Option<CowRcStr<'i>>,
+        /// The keywords of `tech()`, if any.
+        tech: Vec<CowRcStr<'i>>,
+    },
+    /// `local(…)`, with the name of the font face.
+    /// The identifiers of an unquoted name are joined with single spaces.
+    Local(CowRcStr<'i>),
+}
+
+/// Parse the value of the `src` descriptor of an `@font-face` rule,
+/// such as `local("Font Name"), url(a.woff2) format("woff2") tech(variations)`.
+pub fn parse_font_src<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<FontSource<'i>>, BasicParseError<'i>> {
+    input
+        .parse_comma_separated(|input| parse_one_font_source(input).map_err(ParseError::from))
+        .map_err(ParseError::<()>::basic)
+}
+
+fn parse_one_font_source<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<FontSource<'i>, BasicParseError<'i>> {
+    if input
+        .try_parse(|input| input.expect_function_matching("local"))
+        .is_ok()
+    {
+        return input
+            .parse_nested_block(|input| parse_local_name(input).map_err(ParseError::from))
+            .map(FontSource::Local)
+            .map_err(ParseError::<()>::basic);
+    }
+
+    let url = input.expect_url()?;
+    let format = if input
+        .try_parse(|input| input.expect_function_matching("format"))
+        .is_ok()
+    {
+        Some(
+            input
+                .parse_nested_block(|input| Ok(input.expect_ident_or_string()?.clone()))
+                .map_err(ParseError::<()>::basic)?,
+        )
+    } else {
+        None
+    };
+    let tech = if input
+        .try_parse(|input| input.expect_function_matching("tech"))
+        .is_ok()
+    {
+        input
+            .parse_nested_block(|input| {
+                input.parse_comma_separated(|input| Ok(input.expect_ident_cloned()?))
+            })
+            .map_err(ParseError::<()>::basic)?
+    } else {
+        Vec::new()
+    };
+    Ok(FontSource::Url { url, format, tech })
+}
+
+fn parse_local_name<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<CowRcStr<'i>, BasicParseError<'i>> {
+    if let Ok(name) = input.try_parse(|input| input.expect_string_cloned()) {
+        return Ok(name);
+    }
+    let first = input.expect_ident_cloned()?;
+    let mut name = String::new();
+    while let Ok(ident) = input.try_parse(|input| input.expect_ident_cloned()) {
+        if name.is_empty() {
+            name.push_str(&first);
+        }
+        name.push(' ');
+        name.push_str(&ident);
+    }
+    if name.is_empty() {
+        Ok(first)
+    } else {
+        Ok(name.into())
+    }
+}
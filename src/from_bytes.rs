@@ -2,6 +2,8 @@
  * License, v. 2.0. If a copy of the MPL was not distributed with this
  * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
 
+use std::str;
+
 /// Abstraction for avoiding a dependency from cssparser to an encoding library
 pub trait EncodingSupport {
     /// One character encoding
@@ -44,21 +46,42 @@ where
         };
     };
 
-    let prefix = b"@charset \"";
-    if css.starts_with(prefix) {
-        let rest = &css[prefix.len()..];
-        if let Some(label_length) = rest.iter().position(|&b| b == b'"') {
-            if rest[label_length..].starts_with(b"\";") {
-                let label = &rest[..label_length];
-                if let Some(charset_encoding) = E::from_label(label) {
-                    if E::is_utf16_be_or_le(&charset_encoding) {
-                        return E::utf8();
-                    } else {
-                        return charset_encoding;
-                    }
-                }
+    if let Some(label) = charset_label(css) {
+        if let Some(charset_encoding) = E::from_label(label) {
+            if E::is_utf16_be_or_le(&charset_encoding) {
+                return E::utf8();
+            } else {
+                return charset_encoding;
             }
         }
     }
     environment_encoding.unwrap_or_else(E::utf8)
 }
+
+/// Return the label of the `@charset` rule at the start of a stylesheet’s bytes, if any,
+/// such as `iso-8859-1` for `@charset "iso-8859-1";`.
+///
+/// Like in `stylesheet_encoding`, this checks bytes rather than tokens:
+/// the input must start with exactly `@charset "`, and the label ends at the first `";`.
+/// Anything else, including single quotes or extra whitespace, is not an `@charset` rule.
+/// A byte order mark takes precedence over `@charset` when decoding,
+/// so there is no label after one.
+///
+/// The label is not checked against known encodings.
+pub fn get_charset(css: &[u8]) -> Option<&str> {
+    charset_label(css).and_then(|label| str::from_utf8(label).ok())
+}
+
+fn charset_label(css: &[u8]) -> Option<&[u8]> {
+    let prefix = b"@charset \"";
+    if !css.starts_with(prefix) {
+        return None;
+    }
+    let rest = &css[prefix.len()..];
+    let label_length = rest.iter().position(|&b| b == b'"')?;
+    if rest[label_length..].starts_with(b"\";") {
+        Some(&rest[..label_length])
+    } else {
+        None
+    }
+}
@@ -0,0 +1,91 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Helpers for the names carried by `Ident`, `AtKeyword` and `Function` tokens.
+
+use tokenizer::Token;
+use {BasicParseError, Parser};
+
+/// Split a vendor prefix such as `-webkit-` off the start of a name.
+///
+/// Any `-<vendor>-` prefix is recognized, where `<vendor>` is non-empty and has no `-`.
+/// Return the prefix (including both dashes) and the rest of the name,
+/// or `None` and the whole name if it has no vendor prefix.
+/// Custom property names like `--foo` do not have a vendor prefix.
+pub fn split_vendor_prefix(name: &str) -> (Option<&str>, &str) {
+    let bytes = name.as_bytes();
+    if bytes.first() == Some(&b'-') {
+        if let Some(vendor_len) = bytes[1..].iter().position(|&b| b == b'-') {
+            let prefix_len = vendor_len + 2;
+            if vendor_len > 0 && prefix_len < name.len() {
+                return (Some(&name[..prefix_len]), &name[prefix_len..]);
+            }
+        }
+    }
+    (None, name)
+}
+
+/// Return whether a function name, from a `Function` token, is a CSS math function
+/// such as `calc()` or `clamp()`. Matching is case-insensitive in the ASCII range.
+///
+/// https://drafts.csswg.org/css-values-4/#math-function
+pub fn is_math_function(name: &str) -> bool {
+    match_ignore_ascii_case! { name,
+        "calc" | "min" | "max" | "clamp" | "round" | "mod" | "rem" |
+        "sin" | "cos" | "tan" | "asin" | "acos" | "atan" | "atan2" |
+        "pow" | "sqrt" | "hypot" | "log" | "exp" | "abs" | "sign" => true,
+        _ => false,
+    }
+}
+
+/// A CSS-wide keyword, which is valid as the whole value of any property.
+///
+/// https://drafts.csswg.org/css-cascade-5/#defaulting-keywords
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CssWideKeyword {
+    /// `initial`
+    Initial,
+    /// `inherit`
+    Inherit,
+    /// `unset`
+    Unset,
+    /// `revert`
+    Revert,
+    /// `revert-layer`
+    RevertLayer,
+}
+
+impl CssWideKeyword {
+    /// Return the keyword for an identifier, matched case-insensitively in the ASCII range.
+    pub fn from_ident(name: &str) -> Option<Self> {
+        Some(match_ignore_ascii_case! { name,
+            "initial" => CssWideKeyword::Initial,
+            "inherit" => CssWideKeyword::Inherit,
+            "unset" => CssWideKeyword::Unset,
+            "revert" => CssWideKeyword::Revert,
+            "revert-layer" => CssWideKeyword::RevertLayer,
+            _ => return None,
+        })
+    }
+
+    /// Parse a value that consists of a single CSS-wide keyword, such as `inherit`.
+    ///
+    /// Anything else after the keyword, as in `inherit red`, is an error.
+    pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let ident = input.expect_ident()?.clone();
+        let keyword = match CssWideKeyword::from_ident(&ident) {
+            Some(keyword) => keyword,
+            None => return Err(location.new_basic_unexpected_token_error(Token::Ident(ident))),
+        };
+        input.expect_exhausted()?;
+        Ok(keyword)
+    }
+}
+
+/// Return whether an identifier is a CSS-wide keyword such as `inherit`.
+/// Matching is case-insensitive in the ASCII range.
+pub fn is_css_wide_keyword(name: &str) -> bool {
+    CssWideKeyword::from_ident(name).is_some()
+}
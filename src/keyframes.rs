@@ -0,0 +1,36 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-animations/#typedef-keyframe-selector
+
+use tokenizer::Token;
+use {BasicParseError, ParseError, Parser};
+
+/// Parse the comma-separated list of keyframe selectors in the prelude of a keyframe rule,
+/// such as `from`, `50%` or `0%, 100%`.
+///
+/// Return the offsets, where `from` is 0.0 and `to` is 1.0.
+/// Percentages below 0% or above 100% are an error.
+pub fn parse_keyframe_selector<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<f32>, BasicParseError<'i>> {
+    input
+        .parse_comma_separated(|input| parse_one_keyframe_selector(input).map_err(ParseError::from))
+        .map_err(ParseError::<()>::basic)
+}
+
+fn parse_one_keyframe_selector<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<f32, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let token = input.next()?.clone();
+    match token {
+        Token::Ident(ref value) if value.eq_ignore_ascii_case("from") => Ok(0.),
+        Token::Ident(ref value) if value.eq_ignore_ascii_case("to") => Ok(1.),
+        Token::Percentage { unit_value, .. } if unit_value >= 0. && unit_value <= 1. => {
+            Ok(unit_value)
+        }
+        _ => Err(location.new_basic_unexpected_token_error(token)),
+    }
+}
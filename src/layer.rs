@@ -0,0 +1,58 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-cascade-5/#layering
+
+use tokenizer::Token;
+use {is_css_wide_keyword, BasicParseError, CowRcStr, ParseError, Parser};
+
+/// Parse the comma-separated layer names of an `@layer` statement,
+/// such as `base, framework.theme`.
+///
+/// Each name is returned as its dot-separated identifiers.
+/// A CSS-wide keyword is not a valid identifier in a layer name.
+///
+/// The prelude of an `@layer` block rule is a single name, or none for an anonymous layer:
+/// parse it with `parse_layer_name`.
+pub fn parse_layer_names<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<Vec<CowRcStr<'i>>>, BasicParseError<'i>> {
+    input
+        .parse_comma_separated(|input| parse_layer_name(input).map_err(ParseError::from))
+        .map_err(ParseError::<()>::basic)
+}
+
+/// Parse one layer name, such as `framework.theme`, as its dot-separated identifiers.
+///
+/// No whitespace is allowed around the dots.
+pub fn parse_layer_name<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<CowRcStr<'i>>, BasicParseError<'i>> {
+    let mut name = vec![expect_layer_ident(input)?];
+    loop {
+        let dot = input.try_parse(|input| match input.next_including_whitespace() {
+            Ok(&Token::Delim('.')) => Ok(()),
+            _ => Err(()),
+        });
+        if dot.is_err() {
+            return Ok(name);
+        }
+        let location = input.current_source_location();
+        match *input.next_including_whitespace()? {
+            Token::Ident(ref ident) if !is_css_wide_keyword(ident) => name.push(ident.clone()),
+            ref t => return Err(location.new_basic_unexpected_token_error(t.clone())),
+        }
+    }
+}
+
+fn expect_layer_ident<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<CowRcStr<'i>, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let ident = input.expect_ident_cloned()?;
+    if is_css_wide_keyword(&ident) {
+        return Err(location.new_basic_unexpected_token_error(Token::Ident(ident)));
+    }
+    Ok(ident)
+}
@@ -92,24 +92,39 @@ extern crate smallvec;
 
 pub use cssparser_macros::*;
 
+pub use attr::AttrFunction;
 pub use color::{
-    parse_color_keyword, AngleOrNumber, Color, ColorComponentParser, NumberOrPercentage, RGBA,
+    normalize_hex_color, parse_color_hash, parse_color_keyword, AngleOrNumber, Color,
+    ColorComponentParser, NumberOrPercentage, RGBA,
 };
+pub use component_values::ComponentValue;
 pub use cow_rc_str::CowRcStr;
-pub use from_bytes::{stylesheet_encoding, EncodingSupport};
+pub use font_face::{parse_font_src, FontSource};
+pub use from_bytes::{get_charset, stylesheet_encoding, EncodingSupport};
+pub use idents::{is_css_wide_keyword, is_math_function, split_vendor_prefix, CssWideKeyword};
+pub use keyframes::parse_keyframe_selector;
+pub use layer::{parse_layer_name, parse_layer_names};
 pub use nth::parse_nth;
 pub use parser::{BasicParseError, BasicParseErrorKind, ParseError, ParseErrorKind};
-pub use parser::{Delimiter, Delimiters, Parser, ParserInput, ParserState};
-pub use rules_and_declarations::parse_important;
+pub use parser::{BlockType, Delimiter, Delimiters, Parser, ParserInput, ParserState};
+pub use rules_and_declarations::{find_duplicate_declarations, parse_declaration_list};
+pub use rules_and_declarations::{parse_important, strip_important};
 pub use rules_and_declarations::{parse_one_declaration, DeclarationListParser, DeclarationParser};
 pub use rules_and_declarations::{parse_one_rule, RuleListParser};
+pub use rules_and_declarations::{AtRule, Declaration, DeclarationList};
 pub use rules_and_declarations::{AtRuleParser, AtRuleType, QualifiedRuleParser};
+pub use selectors::{parse_selector, specificity, SimpleSelector};
 pub use serializer::{
-    serialize_identifier, serialize_name, serialize_string, CssStringWriter, ToCss,
-    TokenSerializationType,
+    serialize_identifier, serialize_name, serialize_string, serialize_token_stream,
+    CssStringWriter, ToCss, TokenSerializationType,
+};
+pub use tokenizer::{
+    numeric_repr, parse_escape, preprocess, strip_comments, token_streams_equal, tokenize_all,
+    tokens_by_line, unescape, SourceLocation, SourcePosition, Token, TokenKind, TokenizerConfig,
+    TokenizerError, TokenizerErrorKind, Tokens,
 };
-pub use tokenizer::{SourceLocation, SourcePosition, Token};
 pub use unicode_range::UnicodeRange;
+pub use var::VarFunction;
 
 // For macros
 #[doc(hidden)]
@@ -132,13 +147,21 @@ mod tokenizer;
 mod tokenizer {
     include!(concat!(env!("OUT_DIR"), "/tokenizer.rs"));
 }
+mod attr;
 mod color;
+mod component_values;
 mod cow_rc_str;
+mod font_face;
 mod from_bytes;
+mod idents;
+mod keyframes;
+mod layer;
 mod nth;
 mod parser;
+mod selectors;
 mod serializer;
 mod unicode_range;
+mod var;
 
 #[cfg(test)]
 mod size_of_tests;
@@ -6,14 +6,19 @@ use cow_rc_str::CowRcStr;
 use smallvec::SmallVec;
 use std::ops::BitOr;
 use std::ops::Range;
-use tokenizer::{SourceLocation, SourcePosition, Token, Tokenizer};
+use tokenizer::{
+    SourceLocation, SourcePosition, Token, Tokenizer, TokenizerConfig, TokenizerError,
+};
 
 /// A capture of the internal state of a `Parser` (including the position within the input),
-/// obtained from the `Parser::position` method.
+/// obtained from the `Parser::state` method.
 ///
 /// Can be used with the `Parser::reset` method to restore that state.
 /// Should only be used with the `Parser` instance it came from.
-#[derive(Debug, Clone)]
+///
+/// This is a few integers that index into the borrowed input,
+/// so it is cheap to copy and resetting to it does not re-tokenize anything.
+#[derive(Debug, Clone, Copy)]
 pub struct ParserState {
     pub(crate) position: usize,
     pub(crate) current_line_start_position: usize,
@@ -186,6 +191,14 @@ impl<'i> ParserInput<'i> {
         }
     }
 
+    /// Create a new input for a parser, with non-default tokenizer options.
+    pub fn new_with_config(input: &'i str, config: TokenizerConfig) -> ParserInput<'i> {
+        ParserInput {
+            tokenizer: Tokenizer::with_config(input, 0, config),
+            cached_token: None,
+        }
+    }
+
     #[inline]
     fn cached_token_ref(&self) -> &Token<'i> {
         &self.cached_token.as_ref().unwrap().token
@@ -203,29 +216,24 @@ pub struct Parser<'i: 't, 't> {
     stop_before: Delimiters,
 }
 
+/// The kind of a function or simple block, named after its closing bracket.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub(crate) enum BlockType {
+pub enum BlockType {
+    /// A function or a `(…)` block.
     Parenthesis,
+    /// A `[…]` block.
     SquareBracket,
+    /// A `{…}` block.
     CurlyBracket,
 }
 
 impl BlockType {
-    fn opening(token: &Token) -> Option<BlockType> {
-        match *token {
-            Token::Function(_) | Token::ParenthesisBlock => Some(BlockType::Parenthesis),
-            Token::SquareBracketBlock => Some(BlockType::SquareBracket),
-            Token::CurlyBracketBlock => Some(BlockType::CurlyBracket),
-            _ => None,
-        }
-    }
-
-    fn closing(token: &Token) -> Option<BlockType> {
-        match *token {
-            Token::CloseParenthesis => Some(BlockType::Parenthesis),
-            Token::CloseSquareBracket => Some(BlockType::SquareBracket),
-            Token::CloseCurlyBracket => Some(BlockType::CurlyBracket),
-            _ => None,
+    /// Return the token that ends this kind of block.
+    pub fn closing_token(self) -> Token<'static> {
+        match self {
+            BlockType::Parenthesis => Token::CloseParenthesis,
+            BlockType::SquareBracket => Token::CloseSquareBracket,
+            BlockType::CurlyBracket => Token::CloseCurlyBracket,
         }
     }
 }
@@ -374,6 +382,16 @@ impl<'i: 't, 't> Parser<'i, 't> {
         self.input.tokenizer.current_source_location()
     }
 
+    /// The current column number, with each tab advancing to the next multiple of `tab_width`
+    /// columns, as in an editor.
+    /// With a `tab_width` of 1 (or 0), this is the column of `current_source_location`.
+    #[inline]
+    pub fn current_column_with_tab_width(&self, tab_width: u32) -> u32 {
+        self.input
+            .tokenizer
+            .current_column_with_tab_width(tab_width)
+    }
+
     /// The source map URL, if known.
     ///
     /// The source map URL is extracted from a specially formatted
@@ -438,6 +456,16 @@ impl<'i: 't, 't> Parser<'i, 't> {
         self.new_error(BasicParseErrorKind::UnexpectedToken(token))
     }
 
+    /// Return the recoverable errors found by the tokenizer so far,
+    /// such as strings or comments that are not closed before the end of the input.
+    ///
+    /// These do not make the parser return an error, since the tokens are still well-defined.
+    /// Input that is tokenized again after `Parser::reset` does not report errors twice.
+    #[inline]
+    pub fn tokenizer_errors(&self) -> &[TokenizerError] {
+        self.input.tokenizer.errors()
+    }
+
     /// Return the current internal state of the parser (including position within the input).
     ///
     /// This state can later be restored with the `Parser::reset` method.
@@ -450,6 +478,10 @@ impl<'i: 't, 't> Parser<'i, 't> {
     }
 
     /// Advance the input until the next token that’s not whitespace or a comment.
+    ///
+    /// `Parser::next` already does this before returning a token.
+    /// This is useful before `Parser::next_including_whitespace`,
+    /// or to make `Parser::position` point at the next significant token.
     #[inline]
     pub fn skip_whitespace(&mut self) {
         if let Some(block_type) = self.at_start_of.take() {
@@ -478,9 +510,11 @@ impl<'i: 't, 't> Parser<'i, 't> {
     }
 
     /// Restore the internal state of the parser (including position within the input)
-    /// to what was previously saved by the `Parser::position` method.
+    /// to what was previously saved by the `Parser::state` method.
     ///
-    /// Should only be used with `SourcePosition` values from the same `Parser` instance.
+    /// Should only be used with `ParserState` values from the same `Parser` instance,
+    /// or from a nested parser of the same input that has not returned yet.
+    /// Any other state is a logic error, and may cause incorrect tokens or a panic.
     #[inline]
     pub fn reset(&mut self, state: &ParserState) {
         self.input.tokenizer.reset(state);
@@ -528,6 +562,27 @@ impl<'i: 't, 't> Parser<'i, 't> {
         result
     }
 
+    /// Return a copy of the token that the `n + 1`-th call to `Parser::next` would return,
+    /// without consuming any input. `peek_nth(0)` is the next token.
+    ///
+    /// Like `next`, this skips whitespace and comments,
+    /// and a function or block counts as a single token.
+    /// Returns an error if the input (or the current block) ends first.
+    ///
+    /// Each call tokenizes forward from the current position,
+    /// so this is intended for small lookahead distances.
+    pub fn peek_nth(&mut self, n: usize) -> Result<Token<'i>, BasicParseError<'i>> {
+        let start = self.state();
+        let result = self.try_parse(|input| {
+            for _ in 0..n {
+                input.next()?;
+            }
+            Ok(input.next()?.clone())
+        });
+        self.reset(&start);
+        result
+    }
+
     /// Return a slice of the CSS input
     #[inline]
     pub fn slice(&self, range: Range<SourcePosition>) -> &'i str {
@@ -616,12 +671,29 @@ impl<'i: 't, 't> Parser<'i, 't> {
             self.input.cached_token_ref()
         };
 
-        if let Some(block_type) = BlockType::opening(token) {
+        if let Some(block_type) = token.block_start() {
             self.at_start_of = Some(block_type);
         }
         Ok(token)
     }
 
+    /// Same as `Parser::next_including_whitespace_and_comments`,
+    /// but also return the range of the input that the token was read from.
+    ///
+    /// For `Function` and block tokens the range only covers the opening part
+    /// (for example `rgb(` or `{`), not the contents of the block.
+    pub fn next_including_whitespace_and_comments_with_span(
+        &mut self,
+    ) -> Result<(&Token<'i>, Range<SourcePosition>), BasicParseError<'i>> {
+        if let Some(block_type) = self.at_start_of.take() {
+            consume_until_end_of_block(block_type, &mut self.input.tokenizer);
+        }
+        let start = self.position();
+        self.next_including_whitespace_and_comments()?;
+        let end = self.position();
+        Ok((self.input.cached_token_ref(), start..end))
+    }
+
     /// Have the given closure parse something, then check the the input is exhausted.
     /// The result is overridden to `Err(())` if some input remains.
     ///
@@ -646,6 +718,10 @@ impl<'i: 't, 't> Parser<'i, 't> {
     ///
     /// This method retuns `Err(())` the first time that a closure call does,
     /// or if a closure call leaves some input before the next comma or the end of the input.
+    ///
+    /// A trailing comma is not ignored:
+    /// the closure is called once more with an empty input, where it usually fails.
+    /// Commas nested in blocks or functions do not separate values.
     #[inline]
     pub fn parse_comma_separated<F, T, E>(
         &mut self,
@@ -681,6 +757,9 @@ impl<'i: 't, 't> Parser<'i, 't> {
     /// that stops at the end of the block or function (at the matching closing token).
     ///
     /// The result is overridden to `Err(())` if the closure leaves some input before that point.
+    ///
+    /// Either way, this parser then continues after the matching closing token,
+    /// skipping any nested blocks, or at the end of the input if the block is not closed.
     #[inline]
     pub fn parse_nested_block<F, T, E>(&mut self, parse: F) -> Result<T, ParseError<'i, E>>
     where
@@ -983,7 +1062,7 @@ where
             break;
         }
         if let Ok(token) = parser.input.tokenizer.next() {
-            if let Some(block_type) = BlockType::opening(&token) {
+            if let Some(block_type) = token.block_start() {
                 consume_until_end_of_block(block_type, &mut parser.input.tokenizer);
             }
         } else {
@@ -1056,13 +1135,13 @@ where
 
 #[inline(never)]
 #[cold]
-fn consume_until_end_of_block(block_type: BlockType, tokenizer: &mut Tokenizer) {
+pub(crate) fn consume_until_end_of_block(block_type: BlockType, tokenizer: &mut Tokenizer) {
     let mut stack = SmallVec::<[BlockType; 16]>::new();
     stack.push(block_type);
 
     // FIXME: have a special-purpose tokenizer method for this that does less work.
     while let Ok(ref token) = tokenizer.next() {
-        if let Some(b) = BlockType::closing(token) {
+        if let Some(b) = token.block_end() {
             if *stack.last().unwrap() == b {
                 stack.pop();
                 if stack.is_empty() {
@@ -1071,7 +1150,7 @@ fn consume_until_end_of_block(block_type: BlockType, tokenizer: &mut Tokenizer)
             }
         }
 
-        if let Some(block_type) = BlockType::opening(token) {
+        if let Some(block_type) = token.block_start() {
             stack.push(block_type);
         }
     }
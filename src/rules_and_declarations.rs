@@ -4,8 +4,8 @@
 
 // https://drafts.csswg.org/css-syntax/#parsing
 
-use super::{BasicParseError, BasicParseErrorKind, Delimiter};
-use super::{ParseError, Parser, SourceLocation, Token};
+use super::{BasicParseError, BasicParseErrorKind, ComponentValue, Delimiter};
+use super::{ParseError, Parser, SourceLocation, SourcePosition, Token};
 use cow_rc_str::CowRcStr;
 use parser::{parse_nested_block, parse_until_after, parse_until_before, ParserState};
 
@@ -472,6 +472,208 @@ where
     })
 }
 
+/// A declaration with its value kept as component values, such as `margin: 0 !important`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Declaration<'i> {
+    /// The name of the property, unescaped.
+    pub name: CowRcStr<'i>,
+
+    /// The value, without surrounding whitespace and without `!important`.
+    pub value: Vec<ComponentValue<'i>>,
+
+    /// Whether the value ended with `!important`.
+    pub important: bool,
+}
+
+impl<'i> Declaration<'i> {
+    /// Parse a declaration up to the next `;` semicolon at this nesting level
+    /// or the end of the input.
+    ///
+    /// The semicolon is not consumed.
+    /// On error the rest of the declaration is still consumed,
+    /// so that parsing can continue after the semicolon.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        input
+            .parse_until_before(Delimiter::Semicolon, |input| {
+                Declaration::parse_entirely(input).map_err(ParseError::from)
+            })
+            .map_err(ParseError::<()>::basic)
+    }
+
+    fn parse_entirely<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let name = input.expect_ident_cloned()?;
+        input.expect_colon()?;
+        let mut value = ComponentValue::parse_list(input);
+        trim_whitespace(&mut value);
+        let important = strip_important(&mut value);
+        Ok(Declaration {
+            name,
+            value,
+            important,
+        })
+    }
+}
+
+/// An at-rule with its prelude and block kept as component values,
+/// such as `@media (min-width: 10px) { a {} }` or `@import url(a.css) screen;`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtRule<'i> {
+    /// The name of the at-rule, without the `@` and unescaped.
+    pub name: CowRcStr<'i>,
+
+    /// The prelude between the name and the `;` or block, without surrounding whitespace.
+    pub prelude: Vec<ComponentValue<'i>>,
+
+    /// The contents of the `{}` block, or `None` for an at-rule ending with `;`
+    /// or at the end of the input.
+    pub block: Option<Vec<ComponentValue<'i>>>,
+}
+
+impl<'i> AtRule<'i> {
+    /// Parse an at-rule, starting at its `AtKeyword` token.
+    ///
+    /// The at-rule ends after the first `;` or `{}` block at this nesting level,
+    /// or at the end of the input.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        let location = input.current_source_location();
+        let name = match *input.next()? {
+            Token::AtKeyword(ref name) => name.clone(),
+            ref t => return Err(location.new_basic_unexpected_token_error(t.clone())),
+        };
+        let delimiters = Delimiter::Semicolon | Delimiter::CurlyBracketBlock;
+        let mut prelude = input
+            .parse_until_before(delimiters, |input| {
+                Ok::<_, ParseError<()>>(ComponentValue::parse_list(input))
+            })
+            .map_err(ParseError::basic)?;
+        trim_whitespace(&mut prelude);
+        let block = match input.next() {
+            Ok(&Token::CurlyBracketBlock) => Some(
+                input
+                    .parse_nested_block(|input| {
+                        Ok::<_, ParseError<()>>(ComponentValue::parse_list(input))
+                    })
+                    .map_err(ParseError::basic)?,
+            ),
+            _ => None,
+        };
+        Ok(AtRule {
+            name,
+            prelude,
+            block,
+        })
+    }
+}
+
+/// The result of `parse_declaration_list`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeclarationList<'i> {
+    /// Each declaration in order, or the error for an invalid one.
+    pub declarations: Vec<Result<Declaration<'i>, BasicParseError<'i>>>,
+
+    /// The positions of semicolons that do not end a declaration,
+    /// such as the second one in `a: 1;; b: 2`.
+    pub redundant_semicolons: Vec<SourcePosition>,
+}
+
+/// Parse a list of declarations separated by semicolons, such as the contents of a style rule,
+/// and report redundant semicolons rather than skipping them silently.
+///
+/// Unlike `DeclarationListParser` this does not handle at-rules:
+/// they are invalid declarations, which end at the next semicolon.
+pub fn parse_declaration_list<'i, 't>(input: &mut Parser<'i, 't>) -> DeclarationList<'i> {
+    let mut list = DeclarationList {
+        declarations: Vec::new(),
+        redundant_semicolons: Vec::new(),
+    };
+    loop {
+        input.skip_whitespace();
+        let position = input.position();
+        if input.try_parse(|input| input.expect_semicolon()).is_ok() {
+            list.redundant_semicolons.push(position);
+            continue;
+        }
+        if input.is_exhausted() {
+            return list;
+        }
+        list.declarations.push(Declaration::parse(input));
+        if input.expect_semicolon().is_err() {
+            return list;
+        }
+    }
+}
+
+/// Return the index pairs `(earlier, later)` of declarations with the same property name,
+/// such as `color: red; color: blue`.
+///
+/// Names are compared ASCII case-insensitively,
+/// except for custom properties (starting with `--`) which are case-sensitive.
+pub fn find_duplicate_declarations(block: &[Declaration]) -> Vec<(usize, usize)> {
+    let mut duplicates = Vec::new();
+    for (later, declaration) in block.iter().enumerate() {
+        let name = &*declaration.name;
+        let is_custom = name.starts_with("--");
+        for (earlier, previous) in block[..later].iter().enumerate() {
+            let same_name = if is_custom {
+                *previous.name == *name
+            } else {
+                previous.name.eq_ignore_ascii_case(name)
+            };
+            if same_name {
+                duplicates.push((earlier, later))
+            }
+        }
+    }
+    duplicates
+}
+
+fn is_whitespace(value: &ComponentValue) -> bool {
+    matches!(*value, ComponentValue::Token(Token::WhiteSpace(_)))
+}
+
+fn trim_whitespace(value: &mut Vec<ComponentValue>) {
+    let trailing = value.iter().rev().take_while(|v| is_whitespace(v)).count();
+    let end = value.len() - trailing;
+    value.truncate(end);
+    let leading = value.iter().take_while(|v| is_whitespace(v)).count();
+    value.drain(..leading);
+}
+
+/// Remove `!important` from the end of a list of component values,
+/// together with the whitespace around it, and return whether it was there.
+///
+/// Whitespace is allowed between `!` and `important`,
+/// which is matched case-insensitively in the ASCII range.
+/// The contents of blocks and functions are not looked at.
+pub fn strip_important(value: &mut Vec<ComponentValue>) -> bool {
+    let end = value.len() - value.iter().rev().take_while(|v| is_whitespace(v)).count();
+    if end == 0 {
+        return false;
+    }
+    match value[end - 1] {
+        ComponentValue::Token(Token::Ident(ref name)) if name.eq_ignore_ascii_case("important") => {
+        }
+        _ => return false,
+    }
+    let mut bang = end - 1;
+    while bang > 0 && is_whitespace(&value[bang - 1]) {
+        bang -= 1;
+    }
+    if bang > 0 && value[bang - 1] == ComponentValue::Token(Token::Delim('!')) {
+        let before_bang = &value[..bang - 1];
+        let start = before_bang.len()
+            - before_bang
+                .iter()
+                .rev()
+                .take_while(|v| is_whitespace(v))
+                .count();
+        value.truncate(start);
+        true
+    } else {
+        false
+    }
+}
+
 fn parse_at_rule<'i: 't, 't, P, E>(
     start: &ParserState,
     name: CowRcStr<'i>,
@@ -0,0 +1,195 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! Simple selectors, parsed leniently for tools that need their specificity
+//! but not a full selector engine.
+//!
+//! https://drafts.csswg.org/selectors/#specificity-rules
+
+use tokenizer::Token;
+use {BasicParseError, CowRcStr, ParseError, Parser};
+
+/// A simple selector from a complex selector such as `#a > .b c`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SimpleSelector<'i> {
+    /// The universal selector `*`.
+    Universal,
+    /// A type selector such as `p`.
+    Type(CowRcStr<'i>),
+    /// An ID selector such as `#main`.
+    Id(CowRcStr<'i>),
+    /// A class selector such as `.note`.
+    Class(CowRcStr<'i>),
+    /// An attribute selector such as `[href^="http"]`, with the name of the attribute.
+    Attribute(CowRcStr<'i>),
+    /// A pseudo-class such as `:hover` or `:nth-child(2n)`, with its name.
+    PseudoClass(CowRcStr<'i>),
+    /// One of the `:is()`, `:where()`, `:has()` and `:not()` pseudo-classes,
+    /// with its name and the simple selectors of each complex selector of its argument.
+    /// Arguments of `:has()` may start with a combinator.
+    SelectorListPseudoClass(CowRcStr<'i>, Vec<Vec<SimpleSelector<'i>>>),
+    /// A pseudo-element such as `::before`, with its name.
+    /// The legacy single-colon syntax of `:before`, `:after`, `:first-line`
+    /// and `:first-letter` is also a pseudo-element.
+    PseudoElement(CowRcStr<'i>),
+}
+
+/// Parse a complex selector, such as `#a > .b c`,
+/// and return its simple selectors in order.
+///
+/// Combinators are checked but not returned.
+/// Namespace prefixes are not supported.
+/// Selector-list pseudo-classes nested more than 128 levels deep are an error.
+pub fn parse_selector<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<Vec<SimpleSelector<'i>>, BasicParseError<'i>> {
+    parse_selector_with_max_depth(input, 128)
+}
+
+fn parse_selector_with_max_depth<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    max_depth: usize,
+) -> Result<Vec<SimpleSelector<'i>>, BasicParseError<'i>> {
+    let mut selectors = Vec::new();
+    // Whether a compound selector is required next: at the start or after a combinator.
+    let mut expect_compound = true;
+    input.skip_whitespace();
+    loop {
+        let location = input.current_source_location();
+        let token = match input.next_including_whitespace() {
+            Ok(token) => token.clone(),
+            Err(e) => {
+                if expect_compound {
+                    return Err(e);
+                }
+                return Ok(selectors);
+            }
+        };
+        let selector = match token {
+            Token::WhiteSpace(_) => continue,
+            Token::Delim('>') | Token::Delim('+') | Token::Delim('~') if !expect_compound => {
+                expect_compound = true;
+                continue;
+            }
+            Token::Delim('*') => SimpleSelector::Universal,
+            Token::Ident(name) => SimpleSelector::Type(name),
+            Token::IDHash(name) => SimpleSelector::Id(name),
+            Token::Delim('.') => match *input.next_including_whitespace()? {
+                Token::Ident(ref name) => SimpleSelector::Class(name.clone()),
+                ref t => return Err(location.new_basic_unexpected_token_error(t.clone())),
+            },
+            Token::SquareBracketBlock => {
+                let name = input
+                    .parse_nested_block(|input| {
+                        let name = input.expect_ident_cloned()?;
+                        while input.next().is_ok() {}
+                        Ok(name)
+                    })
+                    .map_err(ParseError::<()>::basic)?;
+                SimpleSelector::Attribute(name)
+            }
+            Token::Colon => parse_pseudo(input, max_depth)?,
+            t => return Err(location.new_basic_unexpected_token_error(t)),
+        };
+        selectors.push(selector);
+        expect_compound = false;
+    }
+}
+
+fn parse_pseudo<'i, 't>(
+    input: &mut Parser<'i, 't>,
+    max_depth: usize,
+) -> Result<SimpleSelector<'i>, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    match *input.next_including_whitespace()? {
+        Token::Ident(ref name) => {
+            let is_legacy_pseudo_element = match_ignore_ascii_case! { name,
+                "before" | "after" | "first-line" | "first-letter" => true,
+                _ => false,
+            };
+            if is_legacy_pseudo_element {
+                Ok(SimpleSelector::PseudoElement(name.clone()))
+            } else {
+                Ok(SimpleSelector::PseudoClass(name.clone()))
+            }
+        }
+        Token::Function(ref name) => {
+            let name = name.clone();
+            let (takes_selector_list, relative) = match_ignore_ascii_case! { &name,
+                "is" | "where" | "not" => (true, false),
+                "has" => (true, true),
+                _ => (false, false),
+            };
+            if !takes_selector_list {
+                return Ok(SimpleSelector::PseudoClass(name));
+            }
+            if max_depth == 0 {
+                return Err(location.new_basic_unexpected_token_error(Token::Function(name)));
+            }
+            let arguments = input
+                .parse_nested_block(|input| {
+                    input.parse_comma_separated(|input| {
+                        if relative {
+                            input.skip_whitespace();
+                            let _ = input.try_parse(|input| match input.next() {
+                                Ok(&Token::Delim('>'))
+                                | Ok(&Token::Delim('+'))
+                                | Ok(&Token::Delim('~')) => Ok(()),
+                                _ => Err(()),
+                            });
+                        }
+                        parse_selector_with_max_depth(input, max_depth - 1)
+                            .map_err(ParseError::from)
+                    })
+                })
+                .map_err(ParseError::<()>::basic)?;
+            Ok(SimpleSelector::SelectorListPseudoClass(name, arguments))
+        }
+        Token::Colon => {
+            let location = input.current_source_location();
+            match *input.next_including_whitespace()? {
+                Token::Ident(ref name) | Token::Function(ref name) => {
+                    Ok(SimpleSelector::PseudoElement(name.clone()))
+                }
+                ref t => Err(location.new_basic_unexpected_token_error(t.clone())),
+            }
+        }
+        ref t => Err(location.new_basic_unexpected_token_error(t.clone())),
+    }
+}
+
+/// Return the specificity of a complex selector from its simple selectors,
+/// as the number of ID selectors,
+/// of class selectors, attribute selectors and pseudo-classes,
+/// and of type selectors and pseudo-elements.
+/// The universal selector does not count.
+///
+/// `:where()` counts for nothing,
+/// and the other selector-list pseudo-classes count as their most specific argument.
+pub fn specificity(selectors: &[SimpleSelector]) -> (u32, u32, u32) {
+    let mut specificity = (0, 0, 0);
+    for selector in selectors {
+        match *selector {
+            SimpleSelector::Universal => {}
+            SimpleSelector::SelectorListPseudoClass(ref name, ref arguments) => {
+                if !name.eq_ignore_ascii_case("where") {
+                    let max = arguments
+                        .iter()
+                        .map(|argument| self::specificity(argument))
+                        .max()
+                        .unwrap_or((0, 0, 0));
+                    specificity.0 += max.0;
+                    specificity.1 += max.1;
+                    specificity.2 += max.2;
+                }
+            }
+            SimpleSelector::Id(_) => specificity.0 += 1,
+            SimpleSelector::Class(_)
+            | SimpleSelector::Attribute(_)
+            | SimpleSelector::PseudoClass(_) => specificity.1 += 1,
+            SimpleSelector::Type(_) | SimpleSelector::PseudoElement(_) => specificity.2 += 1,
+        }
+    }
+    specificity
+}
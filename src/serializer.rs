@@ -57,6 +57,15 @@ where
     Ok(())
 }
 
+/// Tokenizing the serialization of a single token on its own gives back the same token, except:
+///
+/// * `BadString` is written without a closing quote, and is read back as a `QuotedString`.
+/// * `Delim('\\')` is read back as an `Ident` unless followed by a newline.
+/// * `Function` and the block tokens only write the opening part of the block.
+///   Their contents and closing token need to be serialized separately.
+///
+/// When serializing a sequence of tokens, see `TokenSerializationType`
+/// for where `/**/` is needed to keep adjacent tokens from being read as one.
 impl<'a> ToCss for Token<'a> {
     fn to_css<W>(&self, dest: &mut W) -> fmt::Result
     where
@@ -106,7 +115,12 @@ impl<'a> ToCss for Token<'a> {
                 write_numeric(value, int_value, has_sign, dest)?;
                 // Disambiguate with scientific notation.
                 let unit = &**unit;
-                if unit == "e" || unit == "E" || unit.starts_with("e-") || unit.starts_with("E-") {
+                let looks_like_exponent = (unit.starts_with('e') || unit.starts_with('E'))
+                    && matches!(
+                        unit.as_bytes().get(1),
+                        None | Some(&b'-') | Some(&(b'0'..=b'9'))
+                    );
+                if looks_like_exponent {
                     dest.write_str("\\65 ")?;
                     serialize_name(&unit[1..], dest)?;
                 } else {
@@ -189,6 +203,10 @@ where
 }
 
 /// Write a CSS identifier, escaping characters as necessary.
+///
+/// Tokenizing the output gives back an `Ident` token with the same value,
+/// except that U+0000 is written as U+FFFD
+/// and that nothing is written for the empty string, which is not a valid identifier.
 pub fn serialize_identifier<W>(mut value: &str, dest: &mut W) -> fmt::Result
 where
     W: fmt::Write,
@@ -267,6 +285,9 @@ where
 }
 
 /// Write a double-quoted CSS string token, escaping content as necessary.
+///
+/// Tokenizing the output gives back a `QuotedString` token with the same value,
+/// except that U+0000 is written as U+FFFD, like the tokenizer would read it.
 pub fn serialize_string<W>(value: &str, dest: &mut W) -> fmt::Result
 where
     W: fmt::Write,
@@ -397,6 +418,29 @@ macro_rules! impl_tocss_for_float {
 impl_tocss_for_float!(f32);
 impl_tocss_for_float!(f64);
 
+/// Write a sequence of tokens, with an empty comment `/**/` between two tokens
+/// wherever `TokenSerializationType::needs_separator_when_before` says
+/// they would otherwise be tokenized differently, such as `a` followed by `b`.
+///
+/// Tokens are written as they are, so block-opening tokens need their own closing tokens.
+/// Consecutive `WhiteSpace` tokens, and the `BadUrl` and `BadString` tokens,
+/// are not preserved when the output is tokenized again.
+pub fn serialize_token_stream<W>(tokens: &[Token], dest: &mut W) -> fmt::Result
+where
+    W: fmt::Write,
+{
+    let mut previous = TokenSerializationType::nothing();
+    for token in tokens {
+        let serialization_type = token.serialization_type();
+        if previous.needs_separator_when_before(serialization_type) {
+            dest.write_str("/**/")?;
+        }
+        token.to_css(dest)?;
+        previous = serialization_type;
+    }
+    Ok(())
+}
+
 /// A category of token. See the `needs_separator_when_before` method.
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub struct TokenSerializationType(TokenSerializationTypeVariants);
@@ -426,6 +470,9 @@ impl TokenSerializationType {
     ///
     /// See https://github.com/w3c/csswg-drafts/issues/4088 for the
     /// `DelimPercent` bits.
+    ///
+    /// `-->` is also separated from a preceding number, `#`, `-` or `@`,
+    /// since `--` can start an identifier.
     pub fn needs_separator_when_before(self, other: TokenSerializationType) -> bool {
         use self::TokenSerializationTypeVariants::*;
         match self.0 {
@@ -447,13 +494,13 @@ impl TokenSerializationType {
             ),
             DelimHash | DelimMinus => matches!(
                 other.0,
-                Ident | Function | UrlOrBadUrl | DelimMinus | Number | Percentage | Dimension
+                Ident | Function | UrlOrBadUrl | DelimMinus | Number | Percentage | Dimension | CDC
             ),
             Number => matches!(
                 other.0,
-                Ident | Function | UrlOrBadUrl | DelimMinus | Number | Percentage | DelimPercent | Dimension
+                Ident | Function | UrlOrBadUrl | DelimMinus | Number | Percentage | DelimPercent | Dimension | CDC
             ),
-            DelimAt => matches!(other.0, Ident | Function | UrlOrBadUrl | DelimMinus),
+            DelimAt => matches!(other.0, Ident | Function | UrlOrBadUrl | DelimMinus | CDC),
             DelimDotOrPlus => matches!(other.0, Number | Percentage | Dimension),
             DelimAssorted | DelimAsterisk => matches!(other.0, DelimEquals),
             DelimBar => matches!(other.0, DelimEquals | DelimBar | DashMatch),
@@ -542,4 +589,21 @@ impl<'a> Token<'a> {
             | Token::SuffixMatch => Other,
         })
     }
+
+    /// Return the length in bytes of what `ToCss::to_css` writes for this token,
+    /// without building the serialized string.
+    pub fn serialized_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+        self.to_css(&mut counter).unwrap();
+        counter.0
+    }
+}
+
+struct ByteCounter(usize);
+
+impl fmt::Write for ByteCounter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
 }
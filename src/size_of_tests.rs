@@ -41,11 +41,11 @@ size_of_test!(token, Token, 32);
 size_of_test!(std_cow_str, Cow<'static, str>, 32);
 size_of_test!(cow_rc_str, CowRcStr, 16);
 
-size_of_test!(tokenizer, ::tokenizer::Tokenizer, 72);
+size_of_test!(tokenizer, ::tokenizer::Tokenizer, 120);
 size_of_test!(
     parser_input,
     ::parser::ParserInput,
-    if cfg!(rustc_has_pr45225) { 136 } else { 144 }
+    if cfg!(rustc_has_pr45225) { 184 } else { 192 }
 );
 size_of_test!(parser, ::parser::Parser, 16);
 size_of_test!(source_position, ::SourcePosition, 8);
@@ -12,11 +12,18 @@ use serde_json::{self, Value, json, Map};
 use self::test::Bencher;
 
 use super::{
-    parse_important, parse_nth, parse_one_declaration, parse_one_rule, stylesheet_encoding,
-    AtRuleParser, AtRuleType, BasicParseError, BasicParseErrorKind, Color, CowRcStr,
-    DeclarationListParser, DeclarationParser, Delimiter, EncodingSupport, ParseError,
-    ParseErrorKind, Parser, ParserInput, QualifiedRuleParser, RuleListParser, SourceLocation,
-    ToCss, Token, TokenSerializationType, UnicodeRange, RGBA,
+    find_duplicate_declarations, get_charset, is_css_wide_keyword, is_math_function,
+    normalize_hex_color, numeric_repr, parse_color_hash, parse_declaration_list, parse_escape,
+    parse_font_src, parse_important, parse_keyframe_selector, parse_layer_name, parse_layer_names,
+    parse_nth, parse_one_declaration, parse_one_rule, parse_selector, preprocess,
+    serialize_identifier, serialize_string, serialize_token_stream, specificity,
+    split_vendor_prefix, strip_comments, strip_important, stylesheet_encoding, token_streams_equal,
+    tokens_by_line, unescape, AtRule, AtRuleParser, AtRuleType, AttrFunction, BasicParseError,
+    BasicParseErrorKind, BlockType, Color, ComponentValue, CowRcStr, CssWideKeyword, Declaration,
+    DeclarationListParser, DeclarationParser, Delimiter, EncodingSupport, FontSource, ParseError,
+    ParseErrorKind, Parser, ParserInput, QualifiedRuleParser, RuleListParser, SimpleSelector,
+    SourceLocation, ToCss, Token, TokenKind, TokenSerializationType, TokenizerConfig,
+    TokenizerError, TokenizerErrorKind, Tokens, UnicodeRange, VarFunction, RGBA,
 };
 
 macro_rules! JArray {
@@ -77,6 +84,15 @@ fn assert_json_eq(results: Value, mut expected: Value, message: &str) {
     }
 }
 
+/// Run the tests of a JSON fixture: an array of alternating inputs and expected results,
+/// in the format of https://github.com/SimonSapin/css-parsing-tests
+/// (see `css-parsing-tests/README.rst` for how results are represented).
+///
+/// `css-parsing-tests` is a git subtree of that repository.
+/// New cases for the functions it covers belong upstream,
+/// and are pulled in with `git subtree pull`.
+/// Fixtures for other functions go next to this file,
+/// loaded with `include_str!` from a `#[test]` that calls `run_json_tests`.
 fn run_raw_json_tests<F: Fn(Value, Value) -> ()>(json_data: &str, run: F) {
     let items = match serde_json::from_str(json_data) {
         Ok(Value::Array(items)) => items,
@@ -250,6 +266,23 @@ fn stylesheet_from_bytes() {
     }
 }
 
+#[test]
+fn charset_label() {
+    assert_eq!(
+        get_charset(b"@charset \"iso-8859-1\"; a {}"),
+        Some("iso-8859-1")
+    );
+    assert_eq!(get_charset(b"@charset \"\";"), Some(""));
+    assert_eq!(get_charset(b"\xEF\xBB\xBF@charset \"utf-8\";"), None);
+    assert_eq!(get_charset(b"@charset 'utf-8';"), None);
+    assert_eq!(get_charset(b"@CHARSET \"utf-8\";"), None);
+    assert_eq!(get_charset(b"@charset  \"utf-8\";"), None);
+    assert_eq!(get_charset(b"@charset \"utf-8\" ;"), None);
+    assert_eq!(get_charset(b"@charset \"utf-8"), None);
+    assert_eq!(get_charset(b" @charset \"utf-8\";"), None);
+    assert_eq!(get_charset(b"@charset \"\xFF\";"), None);
+}
+
 #[test]
 fn expect_no_error_token() {
     let mut input = ParserInput::new("foo 4px ( / { !bar }");
@@ -303,6 +336,28 @@ fn bad_url_slice_not_at_char_boundary() {
     assert_eq!(result, Ok(&Token::BadUrl("9\n۰".into())));
 }
 
+#[test]
+fn bad_url_recovery() {
+    // An escaped `)` does not end the URL, whether or not it is bad.
+    let css = "url(foo\\)bar) url(a b\\)c) d url((nested)) e url(f\\\n) g";
+    let tokens: Vec<_> = Tokens::new(css)
+        .filter(|t| *t != Token::WhiteSpace(" "))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::UnquotedUrl("foo)bar".into()),
+            Token::BadUrl("a b\\)c".into()),
+            Token::Ident("d".into()),
+            Token::BadUrl("(nested".into()),
+            Token::CloseParenthesis,
+            Token::Ident("e".into()),
+            Token::BadUrl("f\\\n".into()),
+            Token::Ident("g".into()),
+        ]
+    );
+}
+
 #[test]
 fn unquoted_url_escaping() {
     let token = Token::UnquotedUrl(
@@ -353,6 +408,35 @@ fn test_expect_url() {
     assert!(parse(&mut input).is_err());
 }
 
+#[test]
+fn expect_with_try_parse_backtracks() {
+    let mut input = ParserInput::new(" ident f( : , 4 50% ");
+    let mut input = Parser::new(&mut input);
+
+    assert!(input
+        .try_parse(|i| i.expect_function_matching("f"))
+        .is_err());
+    assert_eq!(input.expect_ident_cloned(), Ok("ident".into()));
+
+    assert!(input.try_parse(|i| i.expect_ident_cloned()).is_err());
+    assert_eq!(&**input.expect_function().unwrap(), "f");
+    input
+        .parse_nested_block(|i| {
+            assert!(i.try_parse(|i| i.expect_comma()).is_err());
+            i.expect_colon()?;
+            assert!(i.try_parse(|i| i.expect_colon()).is_err());
+            i.expect_comma()?;
+            assert!(i.try_parse(|i| i.expect_percentage()).is_err());
+            assert_eq!(i.expect_number(), Ok(4.));
+            assert!(i.try_parse(|i| i.expect_number()).is_err());
+            assert_eq!(i.expect_percentage(), Ok(0.5));
+            assert!(i.expect_exhausted().is_ok());
+            Ok::<(), ParseError<()>>(())
+        })
+        .unwrap();
+    assert!(input.expect_exhausted().is_ok());
+}
+
 fn run_color_tests<F: Fn(Result<Color, ()>) -> Value>(json_data: &str, to_json: F) {
     run_json_tests(json_data, |input| {
         let result: Result<_, ParseError<()>> =
@@ -400,28 +484,68 @@ fn nth() {
 
 #[test]
 fn unicode_range() {
-    run_json_tests(include_str!("css-parsing-tests/urange.json"), |input| {
-        let result: Result<_, ParseError<()>> = input.parse_comma_separated(|input| {
-            let result = UnicodeRange::parse(input).ok().map(|r| (r.start, r.end));
-            if input.is_exhausted() {
-                Ok(result)
+    // Upstream expects ranges ending above U+10FFFF to be invalid,
+    // but their end is clamped to U+10FFFF instead. See `unicode_range_clamping`.
+    let clamped = [
+        (
+            "u+?????, U+1?????, U+10?????",
+            json!([[0, 1048575], [1048576, 1114111], null]),
+        ),
+        ("u+??????, U+1??????", json!([[0, 1114111], null])),
+        (
+            "u+20-3F, u+3F-3F, u+3F-3E, U+0-110000, U+0-10FFFF, U+100000-2, U+1000000-2, U+10-200000",
+            json!([
+                [32, 63],
+                [63, 63],
+                null,
+                [0, 1114111],
+                [0, 1114111],
+                null,
+                null,
+                [16, 1114111]
+            ]),
+        ),
+    ];
+    run_raw_json_tests(
+        include_str!("css-parsing-tests/urange.json"),
+        |input, expected| {
+            let input = match input {
+                Value::String(input) => input,
+                _ => panic!("Unexpected JSON"),
+            };
+            let expected = match clamped.iter().find(|&(css, _)| *css == input) {
+                Some((_, clamped_expected)) => clamped_expected.clone(),
+                None => expected,
+            };
+            let mut parse_input = ParserInput::new(&input);
+            let result = unicode_ranges_to_json(&mut Parser::new(&mut parse_input));
+            assert_json_eq(result, expected, &input);
+        },
+    );
+}
+
+fn unicode_ranges_to_json(input: &mut Parser) -> Value {
+    let result: Result<_, ParseError<()>> = input.parse_comma_separated(|input| {
+        let result = UnicodeRange::parse(input).ok().map(|r| (r.start, r.end));
+        if input.is_exhausted() {
+            Ok(result)
+        } else {
+            while let Ok(_) = input.next() {}
+            Ok(None)
+        }
+    });
+    result
+        .unwrap()
+        .iter()
+        .map(|v| {
+            if let Some((v0, v1)) = v {
+                json!([v0, v1])
             } else {
-                while let Ok(_) = input.next() {}
-                Ok(None)
+                Value::Null
             }
-        });
-        result.unwrap()
-            .iter()
-            .map(|v|
-                if let Some((v0, v1)) = v{
-                    json!([v0, v1])
-                } else {
-                    Value::Null
-                }
-            )
-            .collect::<Vec<_>>()
-            .to_json()
-    });
+        })
+        .collect::<Vec<_>>()
+        .to_json()
 }
 
 #[test]
@@ -490,6 +614,224 @@ fn serializer(preserve_comments: bool) {
     );
 }
 
+#[test]
+fn bad_token_values() {
+    let css = "url(ab cd) url(e\\)f\"g) 'h\\'i\nj";
+    let tokens: Vec<_> = Tokens::new(css)
+        .filter(|t| !matches!(*t, Token::WhiteSpace(_)))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::BadUrl("ab cd".into()),
+            Token::BadUrl("e\\)f\"g".into()),
+            Token::BadString("h'i".into()),
+            Token::Ident("j".into()),
+        ]
+    );
+}
+
+#[test]
+fn leading_byte_order_mark() {
+    let mut tokens = Tokens::new("\u{FEFF}body");
+    assert_eq!(tokens.current_source_location().column, 1);
+    assert_eq!(tokens.next(), Some(Token::Ident("body".into())));
+    assert_eq!(tokens.next(), None);
+
+    let mut input = ParserInput::new("\u{FEFF}a\u{FEFF}");
+    let mut parser = Parser::new(&mut input);
+    let start = parser.position();
+    assert_eq!(parser.expect_ident_cloned(), Ok("a\u{FEFF}".into()));
+    assert_eq!(parser.slice_from(start), "a\u{FEFF}");
+
+    let css = "body{color:red}\n@media x{a{}}";
+    assert!(Tokens::new(&format!("\u{FEFF}{}", css)).eq(Tokens::new(css)));
+
+    // Only one byte order mark is skipped.
+    let tokens: Vec<_> = Tokens::new("\u{FEFF}\u{FEFF}b").collect();
+    assert_eq!(tokens, vec![Token::Ident("\u{FEFF}b".into())]);
+
+    let tokens: Vec<_> = Tokens::new(" \u{FEFF}b").collect();
+    assert_eq!(
+        tokens,
+        vec![Token::WhiteSpace(" "), Token::Ident("\u{FEFF}b".into())]
+    );
+
+    // Part of a stylesheet can keep its leading U+FEFF.
+    let config = TokenizerConfig {
+        skip_byte_order_mark: false,
+        ..TokenizerConfig::default()
+    };
+    let mut tokens = Tokens::with_config("\u{FEFF}b", config);
+    assert_eq!(tokens.current_column_with_tab_width(1), 1);
+    assert_eq!(tokens.next(), Some(Token::Ident("\u{FEFF}b".into())));
+    assert_eq!(tokens.current_source_location().column, 3);
+    assert_eq!(tokens.current_column_with_tab_width(1), 3);
+}
+
+#[test]
+fn token_stream_serialization_roundtrip() {
+    let css = "a -b --c f( url(u) @d #e #1 'g' 1 -1 +1 .5 1e3 2% 3px 4e \
+               # @ . + - ? $ ^ ~ = | / * % ! < > & \\a \
+               : ; , ~= |= ^= $= *= || <!-- --> ( [ { ) ] } /*x*/";
+    let samples: Vec<_> = Tokens::new(css)
+        .filter(|t| *t != Token::WhiteSpace(" "))
+        .chain(Some(Token::WhiteSpace(" ")))
+        .collect();
+    for a in &samples {
+        for b in &samples {
+            if *a == Token::WhiteSpace(" ") && *b == Token::WhiteSpace(" ") {
+                continue;
+            }
+            let pair = [a.clone(), b.clone()];
+            let mut serialized = String::new();
+            serialize_token_stream(&pair, &mut serialized).unwrap();
+            let tokens: Vec<_> = Tokens::new(&serialized)
+                .filter(|t| !matches!(*t, Token::Comment("")))
+                .collect();
+            assert_eq!(tokens, pair, "serialized as {:?}", serialized);
+        }
+    }
+
+    let mut serialized = String::new();
+    let tokens = [
+        Token::Ident("a".into()),
+        Token::Ident("b".into()),
+        Token::Delim(':'),
+        Token::Number {
+            has_sign: false,
+            value: 1.,
+            int_value: Some(1),
+        },
+        Token::Ident("px".into()),
+    ];
+    serialize_token_stream(&tokens, &mut serialized).unwrap();
+    assert_eq!(serialized, "a/**/b:1/**/px");
+}
+
+#[test]
+fn numbers_reserialized() {
+    for &(css, expected) in &[
+        ("1", "1"),
+        ("+1", "+1"),
+        ("-1", "-1"),
+        ("-0.50", "-0.5"),
+        (".5", "0.5"),
+        ("0", "0"),
+        ("-0", "-0"),
+        ("+0.0", "+0.0"),
+        ("1e2", "100.0"),
+        ("1.5e-3", "0.0015"),
+        ("+2.5%", "+2.5%"),
+        ("-3.0px", "-3.0px"),
+    ] {
+        let tokens: Vec<_> = Tokens::new(css).collect();
+        assert_eq!(tokens.len(), 1, "{:?}", css);
+        let serialized = tokens[0].to_css_string();
+        assert_eq!(serialized, expected);
+        assert_eq!(Tokens::new(&serialized).collect::<Vec<_>>(), tokens);
+    }
+}
+
+#[test]
+fn exponent_at_end_of_input() {
+    fn number(value: f32) -> Token<'static> {
+        Token::Number {
+            has_sign: false,
+            value,
+            int_value: None,
+        }
+    }
+    let tokens = |css| Tokens::new(css).collect::<Vec<_>>();
+    assert_eq!(tokens("1e5"), [number(1e5)]);
+    assert_eq!(tokens("1E5"), [number(1e5)]);
+    assert_eq!(tokens("1e+5"), [number(1e5)]);
+    assert_eq!(tokens("1e-5"), [number(1e-5)]);
+    assert_eq!(
+        tokens("1e"),
+        [Token::Dimension {
+            has_sign: false,
+            value: 1.,
+            int_value: Some(1),
+            unit: "e".into(),
+        }]
+    );
+    assert_eq!(
+        tokens("1e+"),
+        [
+            Token::Dimension {
+                has_sign: false,
+                value: 1.,
+                int_value: Some(1),
+                unit: "e".into(),
+            },
+            Token::Delim('+'),
+        ]
+    );
+}
+
+#[test]
+fn skip_whitespace_before_next_token() {
+    let mut tokens = Tokens::new("   ident /* c */ \n x");
+    tokens.skip_whitespace();
+    assert_eq!(tokens.next(), Some(Token::Ident("ident".into())));
+    tokens.skip_whitespace();
+    tokens.skip_whitespace();
+    assert_eq!(tokens.next(), Some(Token::Ident("x".into())));
+    tokens.skip_whitespace();
+    assert_eq!(tokens.next(), None);
+
+    let mut input = ParserInput::new("a   b");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.next(), Ok(&Token::Ident("a".into())));
+    parser.skip_whitespace();
+    assert_eq!(parser.position().byte_index(), 4);
+    assert_eq!(
+        parser.next_including_whitespace(),
+        Ok(&Token::Ident("b".into()))
+    );
+}
+
+#[test]
+fn remaining_input_shrinks() {
+    let css = "a { b: c }";
+    let mut tokens = Tokens::new(css);
+    assert_eq!(tokens.remaining(), css);
+    tokens.next();
+    assert_eq!(tokens.remaining(), " { b: c }");
+    tokens.skip_whitespace();
+    tokens.next();
+    assert_eq!(tokens.remaining(), " b: c }");
+    let rest = {
+        let fork = tokens.clone();
+        fork.remaining()
+    };
+    while tokens.next().is_some() {}
+    assert_eq!(tokens.remaining(), "");
+    assert_eq!(rest, " b: c }");
+    assert_eq!(tokens.input(), css);
+}
+
+#[test]
+fn speculative_tokenizing_with_clone() {
+    let mut tokens = Tokens::new("a b");
+    assert_eq!(tokens.next(), Some(Token::Ident("a".into())));
+
+    let mut fork = tokens.clone();
+    fork.skip_whitespace();
+    assert_eq!(fork.next(), Some(Token::Ident("b".into())));
+    assert_eq!(fork.next(), None);
+
+    // The original is unaffected.
+    assert_eq!(tokens.next(), Some(Token::WhiteSpace(" ")));
+
+    // Commit by replacing the original with the fork.
+    let mut fork = tokens.clone();
+    assert_eq!(fork.next(), Some(Token::Ident("b".into())));
+    tokens = fork;
+    assert_eq!(tokens.next(), None);
+}
+
 #[test]
 fn serialize_bad_tokens() {
     let mut input = ParserInput::new("url(foo\\) b\\)ar)'ba\\'\"z\n4");
@@ -863,6 +1205,107 @@ fn numeric(b: &mut Bencher) {
     })
 }
 
+#[cfg(feature = "bench")]
+#[bench]
+fn identifiers(b: &mut Bencher) {
+    let css = ".header-nav > li.item-active a:hover { text-decoration: underline }\n".repeat(1000);
+    b.bytes = css.len() as u64;
+    b.iter(|| {
+        let mut input = ParserInput::new(&css);
+        let mut input = Parser::new(&mut input);
+        while let Ok(token) = input.next_including_whitespace_and_comments() {
+            test::black_box(token);
+        }
+    })
+}
+
+#[cfg(feature = "bench")]
+fn stylesheet_rule(i: usize) -> String {
+    format!(
+        ".card-{i} .title, .card-{i}:hover > h2 {{ margin: 0 auto {i}px; \
+         color: rgba({r}, {g}, 64, .5); font: bold 1.25em/1.4 \"Helvetica Neue\", sans-serif; \
+         background: url(img/{i}.png) no-repeat; transition: opacity .2s ease-in-out }}\n",
+        i = i,
+        r = i % 256,
+        g = i * 7 % 256
+    )
+}
+
+#[cfg(feature = "bench")]
+fn bench_tokenize(b: &mut Bencher, css: &str) {
+    b.bytes = css.len() as u64;
+    b.iter(|| {
+        for token in Tokens::new(css) {
+            test::black_box(token);
+        }
+    })
+}
+
+// One long line without optional whitespace, as produced by minifiers.
+#[cfg(feature = "bench")]
+#[bench]
+fn tokenize_minified(b: &mut Bencher) {
+    let css: String = (0..500)
+        .map(|i| {
+            stylesheet_rule(i)
+                .replace(", ", ",")
+                .replace(" {", "{")
+                .replace("; ", ";")
+                .replace(": ", ":")
+                .replace(" }\n", "}")
+        })
+        .collect();
+    bench_tokenize(b, &css)
+}
+
+// Rules on several indented lines, with comments.
+#[cfg(feature = "bench")]
+#[bench]
+fn tokenize_formatted(b: &mut Bencher) {
+    let css: String = (0..500)
+        .map(|i| {
+            format!("/* Card {} */\n", i)
+                + &stylesheet_rule(i)
+                    .replace("{ ", "{\n    ")
+                    .replace("; ", ";\n    ")
+                    .replace(" }", "\n}\n")
+        })
+        .collect();
+    bench_tokenize(b, &css)
+}
+
+// Long selector lists with little else.
+#[cfg(feature = "bench")]
+#[bench]
+fn tokenize_selectors(b: &mut Bencher) {
+    let css: String = (0..500)
+        .map(|i| {
+            format!(
+                "#app main > section.s{i}:not(.hidden) ul li:nth-child(2n+1) a[href^=\"#\"]::after, \
+                 html[dir=rtl] .nav-{i} ~ .panel *:focus-visible {{ x: y }}\n",
+                i = i
+            )
+        })
+        .collect();
+    bench_tokenize(b, &css)
+}
+
+// Generated CSS with deep indentation, where most of the input is whitespace.
+#[cfg(feature = "bench")]
+#[bench]
+fn tokenize_indented(b: &mut Bencher) {
+    let css: String = (0..500)
+        .map(|i| {
+            let indent = " ".repeat(4 * (i % 8 + 1));
+            format!(
+                "{0}.rule-{1} {{\n{0}\t\tcolor: red;\n{0}\t\tmargin: 0  auto;\n{0}}}\n\n",
+                indent, i
+            )
+        })
+        .collect();
+    bench_tokenize(b, &css)
+}
+
 struct JsonParser;
 
 #[test]
@@ -877,6 +1320,42 @@ fn no_stack_overflow_multiple_nested_blocks() {
     while let Ok(..) = input.next() {}
 }
 
+#[test]
+fn component_values_nesting_depth() {
+    let css = "(".repeat(100_000);
+    let mut input = ParserInput::new(&css);
+    let mut parser = Parser::new(&mut input);
+    assert!(ComponentValue::parse(&mut parser).is_err());
+    assert!(parser.is_exhausted());
+
+    let mut input = ParserInput::new(&css);
+    let mut parser = Parser::new(&mut input);
+    assert!(ComponentValue::parse_list_with_max_depth(&mut parser, 512).is_err());
+
+    let css = format!("a {} b", "[".repeat(100_000));
+    let mut input = ParserInput::new(&css);
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(
+        ComponentValue::parse_list(&mut parser),
+        vec![
+            ComponentValue::Token(Token::Ident("a".into())),
+            ComponentValue::Token(Token::WhiteSpace(" ")),
+        ]
+    );
+
+    fn depth_ok(css: &str, max_depth: usize) -> bool {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        ComponentValue::parse_list_with_max_depth(&mut parser, max_depth).is_ok()
+    }
+    assert!(depth_ok("a b", 0));
+    assert!(!depth_ok("a ()", 0));
+    assert!(depth_ok("f(x) [y] {z}", 1));
+    assert!(!depth_ok("f(g())", 1));
+    assert!(depth_ok("f(g()) (a)", 2));
+    assert!(!depth_ok("a {[(b)]} c", 2));
+}
+
 impl<'i> DeclarationParser<'i> for JsonParser {
     type Declaration = Value;
     type Error = ();
@@ -1392,3 +1871,2434 @@ fn utf16_columns() {
         assert_eq!(parser.current_source_location().column, test.1);
     }
 }
+
+#[test]
+fn token_spans() {
+    // Collect the source of each token, including the closing token of each block.
+    fn collect_slices<'i, 't>(parser: &mut Parser<'i, 't>, slices: &mut Vec<&'i str>) {
+        loop {
+            let (is_block, span) = match parser.next_including_whitespace_and_comments_with_span() {
+                Ok((token, span)) => (
+                    matches!(
+                        *token,
+                        Token::Function(_)
+                            | Token::ParenthesisBlock
+                            | Token::SquareBracketBlock
+                            | Token::CurlyBracketBlock
+                    ),
+                    span,
+                ),
+                Err(_) => break,
+            };
+            slices.push(parser.slice(span));
+            if is_block {
+                let contents_end = parser
+                    .parse_nested_block(|parser| -> Result<_, ParseError<()>> {
+                        collect_slices(parser, slices);
+                        Ok(parser.position())
+                    })
+                    .unwrap();
+                slices.push(parser.slice_from(contents_end));
+            }
+        }
+    }
+    fn slices(css: &str) -> Vec<&str> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let mut slices = vec![];
+        collect_slices(&mut parser, &mut slices);
+        slices
+    }
+
+    assert_eq!(
+        slices("a:hover{color:#fff}"),
+        ["a", ":", "hover", "{", "color", ":", "#fff", "}"]
+    );
+    assert_eq!(slices("\\61 b\\63:'d\\'e'"), ["\\61 b\\63", ":", "'d\\'e'"]);
+    assert_eq!(
+        slices("url( a\\)b ) url(\"c\") url(d e)"),
+        ["url( a\\)b )", " ", "url(", "\"c\"", ")", " ", "url(d e)"]
+    );
+    assert_eq!(slices("f([a]{b}"), ["f(", "[", "a", "]", "{", "b", "}", ""]);
+
+    for css in &[
+        "a /* b */ 1.5em \"c\"",
+        "@media (min-width: 1px) { .\\31 a::before { content: \"\\201C\" } }",
+        "a { b: url( \"c\" ) url(d\\ e) url(f g) [h] (i) }",
+        "'unterminated\n\"str\\\"ing",
+    ] {
+        assert_eq!(slices(css).concat(), *css);
+    }
+}
+
+#[test]
+fn attr_function() {
+    fn parse(css: &str) -> Result<(String, Option<String>, Option<String>), ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let attr = AttrFunction::parse(&mut parser).map_err(|_| ())?;
+        Ok((
+            attr.name.to_string(),
+            attr.type_or_unit.map(|t| t.to_string()),
+            attr.fallback.map(|f| f.to_owned()),
+        ))
+    }
+    assert_eq!(parse("attr(title)"), Ok(("title".into(), None, None)));
+    assert_eq!(
+        parse("attr(data-x px)"),
+        Ok(("data-x".into(), Some("px".into()), None))
+    );
+    assert_eq!(
+        parse("attr( data-x , \"default\" )"),
+        Ok(("data-x".into(), None, Some("\"default\"".into())))
+    );
+    assert_eq!(
+        parse("attr(data-count number, calc(1 + 2) 3)"),
+        Ok((
+            "data-count".into(),
+            Some("number".into()),
+            Some("calc(1 + 2) 3".into())
+        ))
+    );
+    assert_eq!(
+        parse("attr(data-x %)"),
+        Ok(("data-x".into(), Some("%".into()), None))
+    );
+    assert_eq!(parse("attr(data-x px em)"), Err(()));
+    assert_eq!(parse("attr(\"title\")"), Err(()));
+    assert_eq!(parse("content(title)"), Err(()));
+}
+
+#[test]
+fn var_function() {
+    fn parse<'a>(css: &'a str) -> Result<(String, Option<Vec<ComponentValue<'a>>>), ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let var = VarFunction::parse(&mut parser).map_err(|_| ())?;
+        Ok((var.name.to_string(), var.fallback))
+    }
+    assert_eq!(parse("var(--x)"), Ok(("--x".into(), None)));
+    assert_eq!(
+        parse("var( --x , 10px )"),
+        Ok((
+            "--x".into(),
+            Some(vec![ComponentValue::Token(Token::Dimension {
+                has_sign: false,
+                value: 10.,
+                int_value: Some(10),
+                unit: "px".into(),
+            })])
+        ))
+    );
+    assert_eq!(
+        parse("var(--x, var(--y) a)"),
+        Ok((
+            "--x".into(),
+            Some(vec![
+                ComponentValue::Function {
+                    name: "var".into(),
+                    arguments: vec![ComponentValue::Token(Token::Ident("--y".into()))],
+                },
+                ComponentValue::Token(Token::WhiteSpace(" ")),
+                ComponentValue::Token(Token::Ident("a".into())),
+            ])
+        ))
+    );
+    assert_eq!(parse("var(--x,)"), Ok(("--x".into(), Some(vec![]))));
+    assert_eq!(parse("var(x)"), Err(()));
+    assert_eq!(parse("var(--x 1)"), Err(()));
+    assert_eq!(parse("var()"), Err(()));
+    assert_eq!(parse("env(--x)"), Err(()));
+
+    assert!(Token::Ident("--main-color".into()).is_custom_property_name());
+    assert!(!Token::Ident("-main-color".into()).is_custom_property_name());
+    assert!(!Token::Function("--f".into()).is_custom_property_name());
+}
+
+#[test]
+fn serialized_len() {
+    let tokens = vec![
+        Token::Ident("color".into()),
+        Token::Ident("-1a b\n".into()),
+        Token::Ident("é\u{0}".into()),
+        Token::AtKeyword("media".into()),
+        Token::Hash("1x".into()),
+        Token::IDHash("foo bar".into()),
+        Token::Number {
+            has_sign: true,
+            value: 1.5,
+            int_value: None,
+        },
+        Token::Number {
+            has_sign: false,
+            value: 1e20,
+            int_value: None,
+        },
+        Token::Percentage {
+            has_sign: false,
+            unit_value: 0.5,
+            int_value: Some(50),
+        },
+        Token::Dimension {
+            has_sign: false,
+            value: 10.,
+            int_value: Some(10),
+            unit: "e3".into(),
+        },
+        Token::QuotedString("it's \"quoted\"\\\n".into()),
+        Token::UnquotedUrl("a b(c)".into()),
+        Token::Function("rgb".into()),
+        Token::WhiteSpace("  \n"),
+        Token::Comment(" c "),
+        Token::Delim('✈'),
+    ];
+    for token in &tokens {
+        assert_eq!(
+            token.serialized_len(),
+            token.to_css_string().len(),
+            "{:?}",
+            token
+        );
+    }
+}
+
+#[test]
+fn comment_tokens() {
+    fn tokens<'a>(css: &'a str) -> Vec<Token<'a>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let mut tokens = vec![];
+        while let Ok(token) = parser.next_including_whitespace_and_comments() {
+            tokens.push(token.clone());
+        }
+        tokens
+    }
+    assert_eq!(
+        tokens("a/* a /* b */b"),
+        vec![
+            Token::Ident("a".into()),
+            Token::Comment(" a /* b "),
+            Token::Ident("b".into()),
+        ]
+    );
+    assert_eq!(
+        tokens("a /* unterminated"),
+        vec![
+            Token::Ident("a".into()),
+            Token::WhiteSpace(" "),
+            Token::Comment(" unterminated"),
+        ]
+    );
+    assert_eq!(tokens("/*"), vec![Token::Comment("")]);
+
+    let css = "/* hi */ a /* b";
+    let comments: Vec<_> = tokens(css)
+        .into_iter()
+        .filter(|t| matches!(*t, Token::Comment(_)))
+        .collect();
+    assert_eq!(comments, vec![Token::Comment(" hi "), Token::Comment(" b")]);
+    assert_eq!(comments[0].comment_source(css), Some("/* hi */"));
+    assert_eq!(comments[1].comment_source(css), Some("/* b"));
+    assert_eq!(comments[0].comment_source("/* hi */"), None);
+    assert_eq!(Token::Comment(" hi ").comment_source(css), None);
+    assert_eq!(Token::Ident("a".into()).comment_source(css), None);
+
+    // Comments are skipped by default.
+    let mut input = ParserInput::new("/* x */ a /* y */");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.next(), Ok(&Token::Ident("a".into())));
+    assert!(parser.is_exhausted());
+}
+
+#[test]
+fn number_source_digits() {
+    let mut input = ParserInput::new("0.1 -12.500e-1");
+    let mut parser = Parser::new(&mut input);
+    let mut digits = vec![];
+    while let Ok((token, span)) = parser.next_including_whitespace_and_comments_with_span() {
+        if let Token::Number { .. } = *token {
+            digits.push(span);
+        }
+    }
+    let digits: Vec<_> = digits.into_iter().map(|span| parser.slice(span)).collect();
+    assert_eq!(digits, ["0.1", "-12.500e-1"]);
+}
+
+#[test]
+fn identifier_serialization_roundtrip() {
+    for value in &[
+        "a",
+        "1a",
+        "-1a",
+        "--1",
+        "-",
+        "--",
+        "-_",
+        "_1",
+        "a b",
+        "#id",
+        ".cls",
+        "a\nb",
+        "\r\t",
+        "\x01\x7F",
+        "é✈🆒",
+        "-\u{1}",
+        "a\\b",
+        "a(b)",
+        "1",
+        "-9",
+        "\u{FFFD}",
+    ] {
+        let mut serialized = String::new();
+        serialize_identifier(value, &mut serialized).unwrap();
+        let mut input = ParserInput::new(&serialized);
+        let mut parser = Parser::new(&mut input);
+        assert_eq!(
+            parser.next_including_whitespace_and_comments(),
+            Ok(&Token::Ident((*value).into())),
+            "{:?} serialized as {:?}",
+            value,
+            serialized
+        );
+        assert!(
+            parser.is_exhausted(),
+            "{:?} serialized as {:?}",
+            value,
+            serialized
+        );
+    }
+}
+
+#[test]
+fn tokens_grouped_by_line() {
+    let css = "a {\n  color:\n    red; }\n";
+    assert_eq!(
+        tokens_by_line(css),
+        vec![
+            vec![
+                Token::Ident("a".into()),
+                Token::WhiteSpace(" "),
+                Token::CurlyBracketBlock,
+                Token::WhiteSpace("\n  "),
+            ],
+            vec![
+                Token::Ident("color".into()),
+                Token::Colon,
+                Token::WhiteSpace("\n    "),
+            ],
+            vec![
+                Token::Ident("red".into()),
+                Token::Semicolon,
+                Token::WhiteSpace(" "),
+                Token::CloseCurlyBracket,
+                Token::WhiteSpace("\n"),
+            ],
+            vec![],
+        ]
+    );
+    assert_eq!(
+        tokens_by_line("/* a\nb */ c"),
+        vec![
+            vec![Token::Comment(" a\nb ")],
+            vec![Token::WhiteSpace(" "), Token::Ident("c".into())],
+        ]
+    );
+    assert_eq!(tokens_by_line(""), vec![vec![]]);
+}
+
+#[test]
+fn single_token_serialization_roundtrip() {
+    for css in &[
+        "foo",
+        "\\31 a",
+        "@media",
+        "#fff",
+        "#\\31 x",
+        "\"a\\\"b\"",
+        "url(a\\)b)",
+        "~=",
+        "|=",
+        "^=",
+        "$=",
+        "*=",
+        "<!--",
+        "-->",
+        "1",
+        "+1.5",
+        "-0.25",
+        "50%",
+        "10px",
+        "1\\65 3",
+        "rgb(",
+        "(",
+        "[",
+        "{",
+        ")",
+        "]",
+        "}",
+        ":",
+        ";",
+        ",",
+        "/* c */",
+        "  ",
+        "&",
+    ] {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let token = parser
+            .next_including_whitespace_and_comments()
+            .unwrap()
+            .clone();
+        assert!(parser.is_exhausted());
+        assert_eq!(token.to_css_string(), *css);
+    }
+}
+
+#[test]
+fn tokens_iterator() {
+    let css = "a { color: red }";
+    let tokens: Vec<_> = Tokens::new(css)
+        .filter(|token| !matches!(*token, Token::WhiteSpace(_)))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Ident("a".into()),
+            Token::CurlyBracketBlock,
+            Token::Ident("color".into()),
+            Token::Colon,
+            Token::Ident("red".into()),
+            Token::CloseCurlyBracket,
+        ]
+    );
+
+    let mut tokens = Tokens::new("a\n/**/");
+    assert_eq!(tokens.next(), Some(Token::Ident("a".into())));
+    assert_eq!(tokens.position().byte_index(), 1);
+    assert_eq!(tokens.next(), Some(Token::WhiteSpace("\n")));
+    assert_eq!(tokens.next(), Some(Token::Comment("")));
+    assert_eq!(
+        tokens.current_source_location(),
+        SourceLocation { line: 1, column: 5 }
+    );
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokens.next(), None);
+
+    // Bad tokens are tokens, not the end of the input.
+    let mut tokens = Tokens::new("'a\nurl(b c)");
+    assert_eq!(tokens.next(), Some(Token::BadString("a".into())));
+    assert_eq!(tokens.next(), Some(Token::WhiteSpace("\n")));
+    assert_eq!(tokens.next(), Some(Token::BadUrl("b c".into())));
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokens.errors().len(), 2);
+}
+
+#[test]
+fn numeric_overflow_does_not_panic() {
+    use std::f32;
+
+    let long_digits = "9".repeat(1000);
+    let css = format!(
+        "{0} -{0} 0.{0} 1e400 -1e400 1e-400 1e99999999999999999999 {0}% {0}px",
+        long_digits
+    );
+    let mut input = ParserInput::new(&css);
+    let mut input = Parser::new(&mut input);
+
+    assert_eq!(
+        input.next(),
+        Ok(&Token::Number {
+            has_sign: false,
+            value: f32::INFINITY,
+            int_value: Some(2147483647),
+        })
+    );
+    assert_eq!(
+        input.next(),
+        Ok(&Token::Number {
+            has_sign: true,
+            value: f32::NEG_INFINITY,
+            int_value: Some(-2147483648),
+        })
+    );
+    assert_eq!(input.expect_number(), Ok(1.));
+    assert_eq!(input.expect_number(), Ok(f32::INFINITY));
+    assert_eq!(input.expect_number(), Ok(f32::NEG_INFINITY));
+    assert_eq!(input.expect_number(), Ok(0.));
+    assert_eq!(input.expect_number(), Ok(f32::INFINITY));
+    assert_eq!(input.expect_percentage(), Ok(f32::INFINITY));
+    assert_eq!(
+        input.next(),
+        Ok(&Token::Dimension {
+            has_sign: false,
+            value: f32::INFINITY,
+            int_value: Some(2147483647),
+            unit: "px".into(),
+        })
+    );
+    assert!(input.is_exhausted());
+}
+
+#[test]
+fn identifier_serialization_roundtrip_generated() {
+    const ALPHABET: &[char] = &[
+        'a', 'Z', '0', '9', '-', '_', ' ', '\t', '\n', '\r', '\x0C', '\x01', '\x1F', '\x7F', '#',
+        '.', '\\', '"', '\'', '(', ')', '{', '}', '/', '*', 'é', '✈', '🆒', '\u{FFFD}',
+    ];
+    // Deterministic linear congruential generator, so that failures are reproducible.
+    let mut seed = 0x2545_F491u32;
+    let mut random = move |bound: usize| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (seed >> 16) as usize % bound
+    };
+    for _ in 0..2000 {
+        let len = 1 + random(8);
+        let value: String = (0..len).map(|_| ALPHABET[random(ALPHABET.len())]).collect();
+        let mut serialized = String::new();
+        serialize_identifier(&value, &mut serialized).unwrap();
+        let tokens: Vec<_> = Tokens::new(&serialized).collect();
+        assert_eq!(
+            tokens,
+            vec![Token::Ident(value.as_str().into())],
+            "{:?} serialized as {:?}",
+            value,
+            serialized
+        );
+    }
+}
+
+#[test]
+fn vendor_prefix() {
+    assert_eq!(
+        split_vendor_prefix("-webkit-transform"),
+        (Some("-webkit-"), "transform")
+    );
+    assert_eq!(
+        split_vendor_prefix("-moz-document"),
+        (Some("-moz-"), "document")
+    );
+    assert_eq!(split_vendor_prefix("-o-a-b"), (Some("-o-"), "a-b"));
+    assert_eq!(split_vendor_prefix("--custom"), (None, "--custom"));
+    assert_eq!(split_vendor_prefix("transform"), (None, "transform"));
+    assert_eq!(split_vendor_prefix("-webkit-"), (None, "-webkit-"));
+    assert_eq!(split_vendor_prefix("-"), (None, "-"));
+}
+
+#[test]
+fn token_stream_equality() {
+    let css = "a { color: red }";
+    assert!(token_streams_equal(css, css, false));
+    assert!(token_streams_equal(css, css, true));
+
+    let reformatted = "\n  a  {\n\tcolor:red /* comment */}\n";
+    assert!(!token_streams_equal(css, reformatted, false));
+    assert!(!token_streams_equal(css, reformatted, true));
+    let reformatted = "\n  a  {\n\tcolor: red /* comment */ }\n";
+    assert!(!token_streams_equal(css, reformatted, false));
+    assert!(token_streams_equal(css, reformatted, true));
+
+    assert!(!token_streams_equal(css, "a { color: blue }", false));
+    assert!(!token_streams_equal(css, "a { color: blue }", true));
+    assert!(!token_streams_equal("a b", "a/**/b", true));
+    assert!(!token_streams_equal(css, "a { color: red", true));
+}
+
+#[test]
+fn comma_separated_integers() {
+    fn parse(css: &str) -> Result<Vec<i32>, ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let result: Result<_, ParseError<()>> =
+            parser.parse_comma_separated(|input| Ok(input.expect_integer()?));
+        result.map_err(|_| ())
+    }
+    assert_eq!(parse("1, 2, 3"), Ok(vec![1, 2, 3]));
+    assert_eq!(parse(" 1 ,2 "), Ok(vec![1, 2]));
+    assert_eq!(parse("1, 2,"), Err(()));
+    assert_eq!(parse(""), Err(()));
+    assert_eq!(parse("1 2, 3"), Err(()));
+
+    // A comma in a nested block belongs to that block.
+    let mut input = ParserInput::new("(1, 2), 3");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> = parser.parse_comma_separated(|input| {
+        if input
+            .try_parse(|input| input.expect_parenthesis_block())
+            .is_ok()
+        {
+            input.parse_nested_block(|input| {
+                input.parse_comma_separated(|input| Ok(input.expect_integer()?))
+            })
+        } else {
+            Ok(vec![input.expect_integer()?])
+        }
+    });
+    assert_eq!(result, Ok(vec![vec![1, 2], vec![3]]));
+
+    // An item that does not parse the contents of its function still ends at the function.
+    let mut input = ParserInput::new("rgb(1,2,3), blue");
+    let mut parser = Parser::new(&mut input);
+    let result: Result<_, ParseError<()>> =
+        parser.parse_comma_separated(|input| Ok(input.next()?.clone()));
+    assert_eq!(
+        result,
+        Ok(vec![
+            Token::Function("rgb".into()),
+            Token::Ident("blue".into())
+        ])
+    );
+}
+
+#[test]
+fn comments_stripped() {
+    assert_eq!(strip_comments("a/*x*/b"), "a b");
+    assert_eq!(strip_comments("1/*x*/px"), "1 px");
+    assert_eq!(strip_comments("a/*x*/{b/**/:c}"), "a{b:c}");
+    assert_eq!(strip_comments("a /*x*/ b"), "a  b");
+    assert_eq!(strip_comments("/* start */a{}/* end */"), "a{}");
+    assert_eq!(strip_comments("a/* unterminated"), "a");
+    assert_eq!(strip_comments("'/*'url(/*)"), "'/*'url(/*)");
+    assert_eq!(strip_comments("no comments"), "no comments");
+
+    // No tokens merge.
+    fn significant_tokens<'a>(css: &'a str) -> Vec<Token<'a>> {
+        Tokens::new(css)
+            .filter(|t| !matches!(*t, Token::Comment(_) | Token::WhiteSpace(_)))
+            .collect()
+    }
+    for css in &[
+        "a/**/b/**/.c/**/-d/**/1/**/%/**/#e/**/f(/**/g/**/)",
+        "</**/!--",
+        "<!/**/--",
+        "--/**/>",
+        "!--/**/>",
+        "\\61/**/a",
+        "\\000061/**/-b",
+        "\\61/**/ b",
+        "<!-/**/-",
+        "-/**/\\\n",
+        "//**/*",
+        "$/**/=",
+        "a /**/ b/**/ c",
+        "a \u{FEFF}/**/b",
+        "a{}\u{FEFF}/* x */b{}",
+        "\u{FEFF}\u{FEFF}/**/a",
+        "/* x */\u{FEFF}a",
+    ] {
+        assert_eq!(
+            significant_tokens(&strip_comments(css)),
+            significant_tokens(css),
+            "{:?}",
+            css
+        );
+    }
+    assert_eq!(strip_comments("</**/!--"), "< !--");
+    assert_eq!(strip_comments("\\61/**/a"), "\\61  a");
+    assert_eq!(strip_comments("\\61/**/:"), "\\61:");
+    assert_eq!(strip_comments("a{}\u{FEFF}/* x */b{}"), "a{}\u{FEFF} b{}");
+    assert_eq!(strip_comments("\u{FEFF}/* x */a"), "\u{FEFF}a");
+}
+
+#[test]
+fn unicode_range_clamping() {
+    fn parse(css: &str) -> Option<(u32, u32)> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        UnicodeRange::parse(&mut parser)
+            .ok()
+            .map(|range| (range.start, range.end))
+    }
+    assert_eq!(parse("U+??????"), Some((0, 0x10FFFF)));
+    assert_eq!(parse("U+10FFFF-110000"), Some((0x10FFFF, 0x10FFFF)));
+    assert_eq!(parse("U+FF????"), None);
+    assert_eq!(parse("U+110000"), None);
+    assert_eq!(parse("U+200-100"), None);
+    assert_eq!(parse("U+FF-00"), None);
+    assert_eq!(parse("U+0-10FFFF"), Some((0, 0x10FFFF)));
+    assert_eq!(parse("U+???"), Some((0, 0xFFF)));
+    assert_eq!(parse("U+1??"), Some((0x100, 0x1FF)));
+    assert_eq!(parse("U+D7FF-E000"), Some((0xD7FF, 0xE000)));
+}
+
+#[test]
+fn string_serialization_roundtrip() {
+    for value in &[
+        "",
+        "plain",
+        "it's",
+        "say \"hi\"",
+        "back\\slash",
+        "line\nbreak",
+        "\r\n\x0C\t",
+        "\x01\x7F",
+        "é✈🆒",
+        "trailing\\",
+        "\nA",
+        "\nf0 \r9",
+    ] {
+        let mut serialized = String::new();
+        serialize_string(value, &mut serialized).unwrap();
+        let tokens: Vec<_> = Tokens::new(&serialized).collect();
+        assert_eq!(
+            tokens,
+            vec![Token::QuotedString((*value).into())],
+            "{:?} serialized as {:?}",
+            value,
+            serialized
+        );
+    }
+
+    let mut serialized = String::new();
+    serialize_string("a\0b", &mut serialized).unwrap();
+    assert_eq!(serialized, "\"a\u{FFFD}b\"");
+
+    // Hex escapes end with a space, so that a following hex digit is not part of them.
+    let mut serialized = String::new();
+    serialize_string("\nA\ng", &mut serialized).unwrap();
+    assert_eq!(serialized, "\"\\a A\\a g\"");
+}
+
+#[test]
+fn reset_and_reread() {
+    fn read_all<'i>(parser: &mut Parser<'i, '_>) -> Vec<Token<'i>> {
+        let mut tokens = vec![];
+        while let Ok(token) = parser.next_including_whitespace() {
+            tokens.push(token.clone());
+        }
+        tokens
+    }
+
+    let mut input = ParserInput::new("a b(c) { d } e");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.expect_ident_cloned(), Ok("a".into()));
+    let state = parser.state();
+    let first = read_all(&mut parser);
+    assert!(parser.is_exhausted());
+    parser.reset(&state);
+    assert_eq!(read_all(&mut parser), first);
+
+    // Resetting to just after a block start token still allows parsing the block.
+    parser.reset(&state);
+    assert_eq!(parser.expect_function_matching("b"), Ok(()));
+    let after_function = parser.state();
+    let result: Result<_, ParseError<()>> =
+        parser.parse_nested_block(|input| Ok(input.expect_ident_cloned()?));
+    assert_eq!(result, Ok("c".into()));
+    parser.reset(&after_function);
+    let result: Result<_, ParseError<()>> =
+        parser.parse_nested_block(|input| Ok(input.expect_ident_cloned()?));
+    assert_eq!(result, Ok("c".into()));
+    assert_eq!(parser.expect_curly_bracket_block(), Ok(()));
+}
+
+#[test]
+fn numeric_overflow_at_integer_boundaries() {
+    let css = "9223372036854775807 9223372036854775808 -9223372036854775809 \
+               2147483648px -2147483649% 2147483647.5";
+    let int_values: Vec<_> = Tokens::new(css)
+        .filter_map(|token| match token {
+            Token::Number { int_value, .. }
+            | Token::Percentage { int_value, .. }
+            | Token::Dimension { int_value, .. } => Some(int_value),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        int_values,
+        vec![
+            Some(2147483647),
+            Some(2147483647),
+            Some(-2147483648),
+            Some(2147483647),
+            Some(-2147483648),
+            None,
+        ]
+    );
+}
+
+#[test]
+fn keyframe_selector() {
+    fn parse(css: &str) -> Result<Vec<f32>, ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parse_keyframe_selector(&mut parser).map_err(|_| ())
+    }
+    assert_eq!(parse("from"), Ok(vec![0.]));
+    assert_eq!(parse("to"), Ok(vec![1.]));
+    assert_eq!(parse("FROM, To"), Ok(vec![0., 1.]));
+    assert_eq!(parse("0%, 50%"), Ok(vec![0., 0.5]));
+    assert_eq!(parse("100%"), Ok(vec![1.]));
+    assert_eq!(parse("150%"), Err(()));
+    assert_eq!(parse("-1%"), Err(()));
+    assert_eq!(parse("50"), Err(()));
+    assert_eq!(parse("from,"), Err(()));
+    assert_eq!(parse("middle"), Err(()));
+}
+
+#[test]
+fn math_functions() {
+    assert!(is_math_function("calc"));
+    assert!(is_math_function("MIN"));
+    assert!(is_math_function("clamp"));
+    assert!(is_math_function("Atan2"));
+    assert!(!is_math_function("rgb"));
+    assert!(!is_math_function("calc-size"));
+    assert!(!is_math_function(""));
+}
+
+#[test]
+fn names_borrow_from_input() {
+    let css = "div.a-b #id @media é-x calc( 'str' url(u)";
+    let borrows_from_input = |value: &str| {
+        let start = css.as_ptr() as usize;
+        let pointer = value.as_ptr() as usize;
+        start <= pointer && pointer < start + css.len()
+    };
+    let mut names = 0;
+    for token in Tokens::new(css) {
+        match token {
+            Token::Ident(ref value)
+            | Token::AtKeyword(ref value)
+            | Token::Hash(ref value)
+            | Token::IDHash(ref value)
+            | Token::Function(ref value)
+            | Token::QuotedString(ref value)
+            | Token::UnquotedUrl(ref value) => {
+                assert!(borrows_from_input(value), "{:?}", value);
+                names += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(names, 8);
+
+    // Escapes need an owned copy, which compares equal to a borrowed value.
+    let css = "d\\69 v";
+    let start = css.as_ptr() as usize;
+    let escaped: Vec<_> = Tokens::new(css).collect();
+    match escaped[0] {
+        Token::Ident(ref value) => {
+            let pointer = value.as_ptr() as usize;
+            assert!(pointer < start || pointer >= start + css.len())
+        }
+        _ => unreachable!(),
+    }
+    assert_eq!(escaped, Tokens::new("div").collect::<Vec<_>>());
+}
+
+#[test]
+fn escapes() {
+    assert_eq!(parse_escape("41 b"), ('A', 3));
+    assert_eq!(parse_escape("41\r\nb"), ('A', 4));
+    assert_eq!(parse_escape("000041b"), ('A', 6));
+    assert_eq!(parse_escape("0"), ('\u{FFFD}', 1));
+    assert_eq!(parse_escape("\0"), ('\u{FFFD}', 1));
+    assert_eq!(parse_escape(""), ('\u{FFFD}', 0));
+    assert_eq!(parse_escape("110000"), ('\u{FFFD}', 6));
+    assert_eq!(parse_escape("D800"), ('\u{FFFD}', 4));
+    assert_eq!(parse_escape("d800"), ('\u{FFFD}', 4));
+    assert_eq!(parse_escape("DFFF"), ('\u{FFFD}', 4));
+    assert_eq!(parse_escape("D7FF"), ('\u{D7FF}', 4));
+    assert_eq!(parse_escape("E000"), ('\u{E000}', 4));
+    assert_eq!(parse_escape("10ffff"), ('\u{10FFFF}', 6));
+    assert_eq!(parse_escape("FFFFFF"), ('\u{FFFD}', 6));
+    assert_eq!(parse_escape("g"), ('g', 1));
+    assert_eq!(parse_escape("éa"), ('é', 2));
+
+    let mut input = ParserInput::new("\\41 b\\\"");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(&**parser.expect_ident().unwrap(), "Ab\"");
+
+    // `\r\n` after a hex escape is one whitespace character, and one newline.
+    let mut tokens = Tokens::new("\\41\r\nB");
+    assert_eq!(tokens.next(), Some(Token::Ident("AB".into())));
+    assert_eq!(tokens.next(), None);
+    assert_eq!(tokens.current_source_location().line, 1);
+}
+
+#[test]
+fn unescape_strings() {
+    assert_eq!(unescape("\\41"), "A");
+    assert_eq!(unescape("\\41 B"), "AB");
+    assert_eq!(unescape("\\41  B"), "A B");
+    assert_eq!(unescape("\\110000"), "\u{FFFD}");
+    assert_eq!(unescape("\\0 x"), "\u{FFFD}x");
+    assert_eq!(unescape("a\\\"b\\\\"), "a\"b\\");
+    assert_eq!(unescape("a\\"), "a\u{FFFD}");
+    assert_eq!(unescape("a\\\nb"), "a\\\nb");
+    assert_eq!(unescape("no escapes"), "no escapes");
+
+    for s in &["a b", "\"\\", "1x\n", "é\u{0}"] {
+        let mut serialized = String::new();
+        serialize_identifier(s, &mut serialized).unwrap();
+        assert_eq!(unescape(&serialized), s.replace('\0', "\u{FFFD}"));
+    }
+}
+
+#[test]
+fn peek_nth() {
+    let mut input = ParserInput::new("a + b(c d) /* x */ e");
+    let mut parser = Parser::new(&mut input);
+    let start = parser.position();
+    assert_eq!(parser.peek_nth(2), Ok(Token::Function("b".into())));
+    assert_eq!(parser.peek_nth(2), Ok(Token::Function("b".into())));
+    assert_eq!(parser.peek_nth(3), Ok(Token::Ident("e".into())));
+    assert!(parser.peek_nth(4).is_err());
+    assert_eq!(parser.position(), start);
+
+    let peeked: Vec<_> = (0..3).map(|n| parser.peek_nth(n).unwrap()).collect();
+    for token in &peeked {
+        assert_eq!(parser.next().unwrap(), token);
+    }
+    assert_eq!(parser.peek_nth(0), Ok(Token::Ident("e".into())));
+
+    // Lookahead stops at the end of a nested block.
+    let mut input = ParserInput::new("(a b) c");
+    let mut parser = Parser::new(&mut input);
+    parser.expect_parenthesis_block().unwrap();
+    parser
+        .parse_nested_block(|input| {
+            assert_eq!(input.peek_nth(1), Ok(Token::Ident("b".into())));
+            assert!(input.peek_nth(2).is_err());
+            input.expect_ident_matching("a")?;
+            input.expect_ident_matching("b")?;
+            Ok::<_, ParseError<()>>(())
+        })
+        .unwrap();
+    assert_eq!(parser.peek_nth(0), Ok(Token::Ident("c".into())));
+}
+
+#[test]
+fn peek_nth_combinators() {
+    fn peek_three<'i>(css: &'i str) -> Vec<Token<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let peeked: Vec<_> = (0..3).filter_map(|n| parser.peek_nth(n).ok()).collect();
+        for token in &peeked {
+            assert_eq!(parser.next().unwrap(), token);
+        }
+        assert!(parser.is_exhausted());
+        peeked
+    }
+    assert_eq!(
+        peek_three("a + b"),
+        vec![
+            Token::Ident("a".into()),
+            Token::Delim('+'),
+            Token::Ident("b".into()),
+        ]
+    );
+    assert_eq!(peek_three("a+b"), peek_three("a + b"));
+    assert_eq!(
+        peek_three("a +1"),
+        vec![
+            Token::Ident("a".into()),
+            Token::Number {
+                has_sign: true,
+                value: 1.,
+                int_value: Some(1),
+            },
+        ]
+    );
+}
+
+#[test]
+fn font_src() {
+    fn parse<'i>(css: &'i str) -> Result<Vec<FontSource<'i>>, BasicParseError<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser
+            .parse_entirely(|p| parse_font_src(p).map_err(ParseError::<()>::from))
+            .map_err(ParseError::basic)
+    }
+
+    assert_eq!(
+        parse("url(a.woff2) format(\"woff2\") tech(variations, palettes)"),
+        Ok(vec![FontSource::Url {
+            url: "a.woff2".into(),
+            format: Some("woff2".into()),
+            tech: vec!["variations".into(), "palettes".into()],
+        }])
+    );
+    assert_eq!(
+        parse("local(\"Font Name\")"),
+        Ok(vec![FontSource::Local("Font Name".into())])
+    );
+    assert_eq!(
+        parse("local(Font   Name)"),
+        Ok(vec![FontSource::Local("Font Name".into())])
+    );
+    assert_eq!(
+        parse("local(Font), url(\"a.otf\") format(opentype), url(b.ttf)"),
+        Ok(vec![
+            FontSource::Local("Font".into()),
+            FontSource::Url {
+                url: "a.otf".into(),
+                format: Some("opentype".into()),
+                tech: vec![],
+            },
+            FontSource::Url {
+                url: "b.ttf".into(),
+                format: None,
+                tech: vec![],
+            },
+        ])
+    );
+    assert!(parse("url(a.woff) tech(variations) format(woff)").is_err());
+    assert!(parse("local(1)").is_err());
+    assert!(parse("url(a.woff),").is_err());
+}
+
+#[test]
+fn layer_names() {
+    fn parse<'i>(css: &'i str) -> Result<Vec<Vec<CowRcStr<'i>>>, BasicParseError<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser
+            .parse_entirely(|p| parse_layer_names(p).map_err(ParseError::<()>::from))
+            .map_err(ParseError::basic)
+    }
+    fn names(css: &str) -> Vec<Vec<String>> {
+        parse(css)
+            .unwrap()
+            .iter()
+            .map(|name| name.iter().map(|ident| ident.to_string()).collect())
+            .collect()
+    }
+
+    assert_eq!(names("base"), [["base"]]);
+    assert_eq!(names("a.b.c"), [["a", "b", "c"]]);
+    assert_eq!(names("x, y.z"), vec![vec!["x"], vec!["y", "z"]]);
+    assert_eq!(
+        names(" base , components,utilities "),
+        [["base"], ["components"], ["utilities"]]
+    );
+    assert!(parse("").is_err());
+    assert!(parse("a.").is_err());
+    assert!(parse("a .b").is_err());
+    assert!(parse("a. b").is_err());
+    assert!(parse("a,").is_err());
+    assert!(parse("a b").is_err());
+    assert!(parse("inherit").is_err());
+    assert!(parse("a.Initial").is_err());
+    assert!(parse("\"a\"").is_err());
+
+    // The prelude of a block rule.
+    let mut input = ParserInput::new("framework.theme {");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(
+        parse_layer_name(&mut parser),
+        Ok(vec!["framework".into(), "theme".into()])
+    );
+}
+
+#[test]
+fn preprocessing() {
+    assert!(match preprocess("a\nb\u{FFFD}") {
+        ::std::borrow::Cow::Borrowed(_) => true,
+        ::std::borrow::Cow::Owned(_) => false,
+    });
+    assert_eq!(preprocess("a\r\nb\r\rc\x0C\0"), "a\nb\n\nc\n\u{FFFD}");
+
+    let expected = vec![
+        Token::Ident("a".into()),
+        Token::WhiteSpace("\n"),
+        Token::Ident("b".into()),
+    ];
+    for css in &["a\r\nb", "a\rb", "a\x0Cb", "a\nb"] {
+        let preprocessed = preprocess(css);
+        assert_eq!(Tokens::new(&preprocessed).collect::<Vec<_>>(), expected);
+        assert!(token_streams_equal(css, &preprocessed, true));
+        let mut tokens = Tokens::new(css);
+        tokens.next();
+        tokens.next();
+        assert_eq!(tokens.current_source_location().line, 1);
+    }
+
+    let preprocessed = preprocess("a\0b");
+    assert!(token_streams_equal("a\0b", &preprocessed, false));
+}
+
+#[test]
+fn component_values() {
+    fn parse<'i>(css: &'i str) -> Vec<ComponentValue<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        ComponentValue::parse_list(&mut parser)
+    }
+    fn ident<'i>(name: &'i str) -> ComponentValue<'i> {
+        ComponentValue::Token(Token::Ident(name.into()))
+    }
+    let space = ComponentValue::Token(Token::WhiteSpace(" "));
+
+    assert_eq!(
+        parse("a(b, c[d]) {e}"),
+        vec![
+            ComponentValue::Function {
+                name: "a".into(),
+                arguments: vec![
+                    ident("b"),
+                    ComponentValue::Token(Token::Comma),
+                    space.clone(),
+                    ident("c"),
+                    ComponentValue::Block {
+                        kind: BlockType::SquareBracket,
+                        contents: vec![ident("d")],
+                    },
+                ],
+            },
+            space.clone(),
+            ComponentValue::Block {
+                kind: BlockType::CurlyBracket,
+                contents: vec![ident("e")],
+            },
+        ]
+    );
+
+    // Blocks open at the end of the input are closed implicitly.
+    assert_eq!(
+        parse("(a [b"),
+        vec![ComponentValue::Block {
+            kind: BlockType::Parenthesis,
+            contents: vec![
+                ident("a"),
+                space.clone(),
+                ComponentValue::Block {
+                    kind: BlockType::SquareBracket,
+                    contents: vec![ident("b")],
+                },
+            ],
+        }]
+    );
+
+    // Unmatched closing tokens are kept as tokens.
+    assert_eq!(
+        parse("(a]) }"),
+        vec![
+            ComponentValue::Block {
+                kind: BlockType::Parenthesis,
+                contents: vec![ident("a"), ComponentValue::Token(Token::CloseSquareBracket)],
+            },
+            space,
+            ComponentValue::Token(Token::CloseCurlyBracket),
+        ]
+    );
+}
+
+#[test]
+fn state_is_copy() {
+    let mut input = ParserInput::new("a, b c(d) e");
+    let mut parser = Parser::new(&mut input);
+    let start = parser.state();
+    let peeked = parser.peek_nth(0).unwrap();
+    let mut tokens = vec![];
+    while let Ok(token) = parser.next() {
+        tokens.push(token.clone());
+    }
+    assert_eq!(tokens[0], peeked);
+
+    for state in [start, start].iter() {
+        parser.reset(state);
+        for token in &tokens {
+            assert_eq!(parser.next().unwrap(), token);
+        }
+        assert!(parser.is_exhausted());
+    }
+}
+
+#[test]
+fn declaration() {
+    fn parse<'i>(css: &'i str) -> Result<Declaration<'i>, BasicParseError<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        Declaration::parse(&mut parser)
+    }
+    fn token<'i>(token: Token<'i>) -> ComponentValue<'i> {
+        ComponentValue::Token(token)
+    }
+    let zero = token(Token::Number {
+        has_sign: false,
+        value: 0.,
+        int_value: Some(0),
+    });
+
+    assert_eq!(
+        parse("color: red"),
+        Ok(Declaration {
+            name: "color".into(),
+            value: vec![token(Token::Ident("red".into()))],
+            important: false,
+        })
+    );
+    for css in &[
+        "margin : 0 !important",
+        "margin:0!IMPORTANT",
+        "margin: 0 ! important ",
+    ] {
+        assert_eq!(
+            parse(css),
+            Ok(Declaration {
+                name: "margin".into(),
+                value: vec![zero.clone()],
+                important: true,
+            })
+        );
+    }
+    assert_eq!(
+        parse("margin: 0 important"),
+        Ok(Declaration {
+            name: "margin".into(),
+            value: vec![
+                zero.clone(),
+                token(Token::WhiteSpace(" ")),
+                token(Token::Ident("important".into())),
+            ],
+            important: false,
+        })
+    );
+    assert!(parse(": red").is_err());
+    assert!(parse("color red").is_err());
+    assert!(parse("!important").is_err());
+
+    // Parsing stops before the semicolon, even after an error.
+    let mut input = ParserInput::new("color; width: {;} 1px; height");
+    let mut parser = Parser::new(&mut input);
+    assert!(Declaration::parse(&mut parser).is_err());
+    assert_eq!(parser.expect_semicolon(), Ok(()));
+    assert_eq!(Declaration::parse(&mut parser).unwrap().value.len(), 3);
+    assert_eq!(parser.expect_semicolon(), Ok(()));
+    assert!(Declaration::parse(&mut parser).is_err());
+    assert!(parser.is_exhausted());
+}
+
+#[test]
+fn strip_important_values() {
+    fn parse<'i>(css: &'i str) -> Vec<ComponentValue<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        ComponentValue::parse_list(&mut parser)
+    }
+    fn strip<'i>(css: &'i str) -> (bool, Vec<ComponentValue<'i>>) {
+        let mut value = parse(css);
+        (strip_important(&mut value), value)
+    }
+    assert_eq!(strip("red ! important"), (true, parse("red")));
+    assert_eq!(strip(" red !IMPORTANT "), (true, parse(" red")));
+    assert_eq!(strip("!important"), (true, vec![]));
+    assert_eq!(strip("!important !important"), (true, parse("!important")));
+    for css in &[
+        "red !importantX",
+        "red ! important-ish",
+        "red important",
+        "red",
+        "f(!important)",
+        "",
+    ] {
+        assert_eq!(strip(css), (false, parse(css)));
+    }
+}
+
+#[test]
+fn duplicate_declarations() {
+    let mut input = ParserInput::new(
+        "color: red; --Accent: blue; width: 0; COLOR: blue; --accent: red; color: green",
+    );
+    let mut parser = Parser::new(&mut input);
+    let mut block = vec![];
+    loop {
+        block.push(Declaration::parse(&mut parser).unwrap());
+        if parser.expect_semicolon().is_err() {
+            break;
+        }
+    }
+    assert_eq!(block.len(), 6);
+    assert_eq!(
+        find_duplicate_declarations(&block),
+        vec![(0, 3), (0, 5), (3, 5)]
+    );
+    assert_eq!(find_duplicate_declarations(&block[1..3]), vec![]);
+}
+
+#[test]
+fn redundant_semicolons() {
+    let css = "a:1;;; b:2";
+    let mut input = ParserInput::new(css);
+    let mut parser = Parser::new(&mut input);
+    let list = parse_declaration_list(&mut parser);
+    let names: Vec<_> = list
+        .declarations
+        .iter()
+        .map(|d| d.as_ref().unwrap().name.to_string())
+        .collect();
+    assert_eq!(names, ["a", "b"]);
+    let offsets: Vec<_> = list
+        .redundant_semicolons
+        .iter()
+        .map(|position| position.byte_index())
+        .collect();
+    assert_eq!(offsets, [4, 5]);
+
+    let mut input = ParserInput::new(" ; a: 1; b ; c: {;}; ");
+    let mut parser = Parser::new(&mut input);
+    let list = parse_declaration_list(&mut parser);
+    assert_eq!(list.declarations.len(), 3);
+    assert!(list.declarations[1].is_err());
+    assert_eq!(list.redundant_semicolons.len(), 1);
+    assert!(parser.is_exhausted());
+}
+
+#[test]
+fn nested_block_skips_to_matching_close() {
+    let mut input = ParserInput::new("{ a { b } c ( ] ) } d");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.expect_curly_bracket_block(), Ok(()));
+    let result: Result<_, ParseError<()>> = parser.parse_nested_block(|input| {
+        assert_eq!(input.expect_ident_matching("a"), Ok(()));
+        assert_eq!(input.expect_curly_bracket_block(), Ok(()));
+        Ok(())
+    });
+    assert!(result.is_err());
+    assert_eq!(parser.expect_ident_matching("d"), Ok(()));
+    assert!(parser.is_exhausted());
+
+    // The closure can return early with an error too.
+    let mut input = ParserInput::new("[a [b]] c");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.expect_square_bracket_block(), Ok(()));
+    let result: Result<(), _> =
+        parser.parse_nested_block(|input| Err(input.new_custom_error::<_, ()>(())));
+    assert!(result.is_err());
+    assert_eq!(parser.expect_ident_matching("c"), Ok(()));
+
+    // A block that is not closed ends at the end of the input.
+    let mut input = ParserInput::new("(a (b) c");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.expect_parenthesis_block(), Ok(()));
+    let result: Result<_, ParseError<()>> = parser.parse_nested_block(|input| {
+        input.expect_ident_matching("a")?;
+        input.expect_parenthesis_block()?;
+        input.expect_ident_matching("c")?;
+        Ok(())
+    });
+    assert_eq!(result, Ok(()));
+    assert!(parser.is_exhausted());
+}
+
+#[test]
+fn unterminated_string_and_comment_errors() {
+    fn errors(css: &str) -> Vec<TokenizerError> {
+        let mut tokens = Tokens::new(css);
+        while tokens.next().is_some() {}
+        tokens.errors().to_vec()
+    }
+    fn error(kind: TokenizerErrorKind, line: u32, column: u32) -> TokenizerError {
+        TokenizerError {
+            kind,
+            location: SourceLocation { line, column },
+        }
+    }
+
+    assert_eq!(errors("a 'b' /* c */ \"d\\\"\""), vec![]);
+    assert_eq!(
+        errors("a\n  \"bc"),
+        vec![error(TokenizerErrorKind::UnterminatedString, 1, 3)]
+    );
+    assert_eq!(
+        errors("a 'b\\'c\\"),
+        vec![error(TokenizerErrorKind::UnterminatedString, 0, 3)]
+    );
+    assert_eq!(
+        errors("a /* b\n c"),
+        vec![error(TokenizerErrorKind::UnterminatedComment, 0, 3)]
+    );
+    // The first string ends at the newline as a BadString.
+    assert_eq!(
+        errors("'a\n'"),
+        vec![
+            error(TokenizerErrorKind::BadString, 0, 3),
+            error(TokenizerErrorKind::UnterminatedString, 1, 1),
+        ]
+    );
+    assert_eq!(
+        errors("\"unterminated"),
+        vec![error(TokenizerErrorKind::UnterminatedString, 0, 1)]
+    );
+    assert_eq!(
+        errors("url(a b) url(c\"d) url(e\\\n)"),
+        vec![
+            error(TokenizerErrorKind::BadUrl, 0, 7),
+            error(TokenizerErrorKind::BadUrl, 0, 15),
+            error(TokenizerErrorKind::BadUrl, 0, 24),
+        ]
+    );
+    assert_eq!(
+        errors("a\\\nb c\\"),
+        vec![
+            error(TokenizerErrorKind::InvalidEscape, 0, 2),
+            error(TokenizerErrorKind::InvalidEscape, 1, 4),
+        ]
+    );
+    // Escapes of surrogates or large values are not errors.
+    assert_eq!(errors("\\D800 \\110000"), vec![]);
+
+    // An error inside an unterminated string comes after the string's own error.
+    let mut tokens = Tokens::with_config(
+        "a \"b\0c",
+        TokenizerConfig {
+            error_on_null: true,
+            ..TokenizerConfig::default()
+        },
+    );
+    while tokens.next().is_some() {}
+    assert_eq!(
+        tokens.errors(),
+        &[
+            error(TokenizerErrorKind::UnterminatedString, 0, 3),
+            error(TokenizerErrorKind::NullCharacter, 0, 5),
+        ]
+    );
+
+    // The token stream is unchanged.
+    assert_eq!(
+        Tokens::new("\"bc").collect::<Vec<_>>(),
+        vec![Token::QuotedString("bc".into())]
+    );
+
+    // Errors are reported once even when the parser tokenizes the input again.
+    let mut input = ParserInput::new("a /* b");
+    let mut parser = Parser::new(&mut input);
+    let start = parser.state();
+    assert!(parser.next().is_ok());
+    assert!(parser.next().is_err());
+    parser.reset(&start);
+    while parser.next_including_whitespace_and_comments().is_ok() {}
+    assert_eq!(
+        parser.tokenizer_errors(),
+        &[error(TokenizerErrorKind::UnterminatedComment, 0, 3)]
+    );
+
+    let config = TokenizerConfig {
+        error_on_null: true,
+        ..TokenizerConfig::default()
+    };
+    let mut input = ParserInput::new_with_config("a \"b\0c", config);
+    let mut parser = Parser::new(&mut input);
+    let start = parser.state();
+    while parser.next().is_ok() {}
+    parser.reset(&start);
+    while parser.next().is_ok() {}
+    assert_eq!(
+        parser.tokenizer_errors(),
+        &[
+            error(TokenizerErrorKind::UnterminatedString, 0, 3),
+            error(TokenizerErrorKind::NullCharacter, 0, 5),
+        ]
+    );
+}
+
+#[test]
+fn declaration_values() {
+    let mut input = ParserInput::new("margin:0; color: red ! important ; color: rgba(0,0,0,.5)");
+    let mut parser = Parser::new(&mut input);
+    let list = parse_declaration_list(&mut parser);
+    let declarations: Vec<_> = list.declarations.into_iter().map(Result::unwrap).collect();
+    assert_eq!(list.redundant_semicolons, vec![]);
+
+    assert_eq!(declarations[0].name, "margin");
+    assert_eq!(declarations[0].value.len(), 1);
+    assert!(!declarations[0].important);
+
+    assert_eq!(declarations[1].name, "color");
+    assert_eq!(
+        declarations[1].value,
+        vec![ComponentValue::Token(Token::Ident("red".into()))]
+    );
+    assert!(declarations[1].important);
+
+    match declarations[2].value[..] {
+        [ComponentValue::Function {
+            ref name,
+            ref arguments,
+        }] => {
+            assert_eq!(&**name, "rgba");
+            assert_eq!(arguments.len(), 7);
+            assert_eq!(
+                arguments[6],
+                ComponentValue::Token(Token::Number {
+                    has_sign: false,
+                    value: 0.5,
+                    int_value: None,
+                })
+            );
+        }
+        ref value => panic!("unexpected value {:?}", value),
+    }
+    assert!(!declarations[2].important);
+}
+
+#[test]
+fn css_wide_keywords() {
+    fn parse(css: &str) -> Option<CssWideKeyword> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        CssWideKeyword::parse(&mut parser).ok()
+    }
+
+    assert_eq!(parse("initial"), Some(CssWideKeyword::Initial));
+    assert_eq!(parse(" Inherit "), Some(CssWideKeyword::Inherit));
+    assert_eq!(parse("UNSET"), Some(CssWideKeyword::Unset));
+    assert_eq!(parse("revert /**/"), Some(CssWideKeyword::Revert));
+    assert_eq!(parse("revert-layer"), Some(CssWideKeyword::RevertLayer));
+    assert_eq!(parse("inherit red"), None);
+    assert_eq!(parse("red inherit"), None);
+    assert_eq!(parse("\"inherit\""), None);
+    assert_eq!(parse("default"), None);
+
+    assert!(is_css_wide_keyword("Revert-Layer"));
+    assert!(!is_css_wide_keyword("revert-"));
+    assert!(!is_css_wide_keyword("none"));
+}
+
+#[test]
+fn selector_specificity() {
+    fn parse<'i>(css: &'i str) -> Result<Vec<SimpleSelector<'i>>, BasicParseError<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parse_selector(&mut parser)
+    }
+    fn parse_specificity(css: &str) -> (u32, u32, u32) {
+        specificity(&parse(css).unwrap())
+    }
+
+    assert_eq!(
+        parse("#a .b c"),
+        Ok(vec![
+            SimpleSelector::Id("a".into()),
+            SimpleSelector::Class("b".into()),
+            SimpleSelector::Type("c".into()),
+        ])
+    );
+    assert_eq!(parse_specificity("#a .b c"), (1, 1, 1));
+    assert_eq!(parse_specificity("*"), (0, 0, 0));
+    assert_eq!(parse_specificity("[x].y"), (0, 2, 0));
+    assert_eq!(
+        parse_specificity("a[href^='http' i] > *:hover + b::before"),
+        (0, 2, 3)
+    );
+    assert_eq!(
+        parse_specificity("li:nth-child(2n + 1) ~ p:first-line"),
+        (0, 1, 3)
+    );
+    assert_eq!(parse_specificity(" #x#y ::part(label) "), (2, 0, 1));
+
+    assert_eq!(
+        parse(":is(.a, .b)"),
+        Ok(vec![SimpleSelector::SelectorListPseudoClass(
+            "is".into(),
+            vec![
+                vec![SimpleSelector::Class("a".into())],
+                vec![SimpleSelector::Class("b".into())],
+            ]
+        )])
+    );
+    assert_eq!(
+        parse("a:not(p)"),
+        Ok(vec![
+            SimpleSelector::Type("a".into()),
+            SimpleSelector::SelectorListPseudoClass(
+                "not".into(),
+                vec![vec![SimpleSelector::Type("p".into())]]
+            ),
+        ])
+    );
+    assert_eq!(
+        parse(":is(:not(.x))"),
+        Ok(vec![SimpleSelector::SelectorListPseudoClass(
+            "is".into(),
+            vec![vec![SimpleSelector::SelectorListPseudoClass(
+                "not".into(),
+                vec![vec![SimpleSelector::Class("x".into())]]
+            )]]
+        )])
+    );
+    assert_eq!(parse_specificity(":is(.a, #b c)"), (1, 0, 1));
+    assert_eq!(parse_specificity("p:not(p, .a .b)"), (0, 2, 1));
+    assert_eq!(parse_specificity(":is(:not(.x)) a"), (0, 1, 1));
+    assert_eq!(parse_specificity(":WHERE(#a, .b) c"), (0, 0, 1));
+    assert_eq!(parse_specificity("a:has(> img, + .b)"), (0, 1, 1));
+    assert!(parse(":is(.a,)").is_err());
+    assert!(parse(":not(> a)").is_err());
+
+    assert!(parse("").is_err());
+    assert!(parse("a >").is_err());
+    assert!(parse("> a").is_err());
+    assert!(parse("a > > b").is_err());
+    assert!(parse(". a").is_err());
+    assert!(parse("#1").is_err());
+    assert!(parse("[]").is_err());
+    assert!(parse("a: b").is_err());
+    assert!(parse("a, b").is_err());
+
+    // Nesting is bounded.
+    let nested = format!("{}a{}", ":is(".repeat(128), ")".repeat(128));
+    assert_eq!(parse_specificity(&nested), (0, 0, 1));
+    assert!(parse(&format!(":is({})", nested)).is_err());
+    assert!(parse(&":not(".repeat(100_000)).is_err());
+}
+
+#[test]
+fn at_rule() {
+    fn parse<'i>(css: &'i str) -> Result<AtRule<'i>, BasicParseError<'i>> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        let rule = AtRule::parse(&mut parser);
+        assert!(rule.is_err() || parser.is_exhausted());
+        rule
+    }
+    fn token<'i>(token: Token<'i>) -> ComponentValue<'i> {
+        ComponentValue::Token(token)
+    }
+
+    assert_eq!(
+        parse("@charset \"utf-8\";"),
+        Ok(AtRule {
+            name: "charset".into(),
+            prelude: vec![token(Token::QuotedString("utf-8".into()))],
+            block: None,
+        })
+    );
+    assert_eq!(
+        parse("@import url(a.css) screen;"),
+        Ok(AtRule {
+            name: "import".into(),
+            prelude: vec![
+                token(Token::UnquotedUrl("a.css".into())),
+                token(Token::WhiteSpace(" ")),
+                token(Token::Ident("screen".into())),
+            ],
+            block: None,
+        })
+    );
+
+    assert_eq!(
+        parse("@media screen {}"),
+        Ok(AtRule {
+            name: "media".into(),
+            prelude: vec![token(Token::Ident("screen".into()))],
+            block: Some(vec![]),
+        })
+    );
+
+    let media = parse("@media (min-width: 10px) { a{} }").unwrap();
+    assert_eq!(media.name, "media");
+    match media.prelude[..] {
+        [ComponentValue::Block {
+            kind: BlockType::Parenthesis,
+            ref contents,
+        }] => assert_eq!(contents.len(), 4),
+        ref prelude => panic!("unexpected prelude {:?}", prelude),
+    }
+    assert_eq!(
+        media.block,
+        Some(vec![
+            token(Token::WhiteSpace(" ")),
+            token(Token::Ident("a".into())),
+            ComponentValue::Block {
+                kind: BlockType::CurlyBracket,
+                contents: vec![],
+            },
+            token(Token::WhiteSpace(" ")),
+        ])
+    );
+
+    // Best effort at the end of the input.
+    assert_eq!(parse("@foo bar").unwrap().prelude.len(), 1);
+    assert_eq!(
+        parse("@font-face { src: url(a")
+            .unwrap()
+            .block
+            .unwrap()
+            .len(),
+        5
+    );
+    assert!(parse("foo;").is_err());
+
+    // Only the first rule is consumed.
+    let mut input = ParserInput::new("@a; @b {} @c");
+    let mut parser = Parser::new(&mut input);
+    let names: Vec<_> = (0..3)
+        .map(|_| AtRule::parse(&mut parser).unwrap().name)
+        .collect();
+    assert_eq!(names, ["a", "b", "c"]);
+}
+
+#[test]
+fn case_insensitive_token_helpers() {
+    assert!(Token::Function("URL".into()).is_function("url"));
+    assert!(Token::Function("url".into()).is_function("Url"));
+    assert!(!Token::Function("url".into()).is_ident("url"));
+    assert!(Token::Ident("AUTO".into()).is_ident("auto"));
+    assert!(!Token::Ident("auto".into()).is_function("auto"));
+    assert!(!Token::QuotedString("auto".into()).is_ident("auto"));
+    assert!(Token::Ident("RED".into()).is_ident("red"));
+    assert!(Token::AtKeyword("Media".into()).is_at_keyword("MEDIA"));
+    assert!(!Token::AtKeyword("media".into()).is_at_keyword("@media"));
+    assert!(!Token::Ident("media".into()).is_at_keyword("media"));
+    assert!(!Token::Function("media".into()).is_at_keyword("media"));
+
+    // Only ASCII letters are case-insensitive.
+    assert!(!Token::Ident("İ".into()).is_ident("i"));
+    assert!(!Token::Ident("\u{212A}".into()).is_ident("k"));
+    assert!(!Token::Ident("é".into()).is_ident("É"));
+    assert!(!Token::Ident("CAFÉ".into()).is_ident("café"));
+    assert!(Token::Ident("CAFé".into()).is_ident("café"));
+
+    assert_eq!(Token::AtKeyword("Media".into()).at_keyword(), Some("Media"));
+    assert_eq!(Token::Ident("media".into()).at_keyword(), None);
+    let keywords: Vec<_> = Tokens::new("@IMPORT a; @media{}")
+        .filter_map(|t| t.at_keyword().map(|name| name.to_ascii_lowercase()))
+        .collect();
+    assert_eq!(keywords, ["import", "media"]);
+}
+
+#[test]
+fn dimension_units() {
+    let tokens: Vec<_> = Tokens::new("10PX 10px 1\\45m 2Ä 3% 4").collect();
+    let units: Vec<_> = tokens.iter().map(|t| t.dimension_unit()).collect();
+    assert_eq!(
+        units,
+        [
+            Some("PX"),
+            None,
+            Some("px"),
+            None,
+            Some("Em"),
+            None,
+            Some("Ä"),
+            None,
+            None,
+            None,
+            None
+        ]
+    );
+    assert_eq!(tokens[0].dimension_unit_lowercase(), Some("px".into()));
+    assert_eq!(
+        tokens[0].dimension_unit_lowercase(),
+        tokens[2].dimension_unit_lowercase()
+    );
+    assert!(tokens[0].dimension_unit() != tokens[2].dimension_unit());
+    assert!(matches!(
+        tokens[2].dimension_unit_lowercase(),
+        Some(::std::borrow::Cow::Borrowed("px"))
+    ));
+    assert_eq!(tokens[4].dimension_unit_lowercase(), Some("em".into()));
+    assert_eq!(tokens[6].dimension_unit_lowercase(), Some("Ä".into()));
+    assert_eq!(tokens[8].dimension_unit_lowercase(), None);
+
+    let matching = Tokens::new("10PX 10Px 10px 10\\70x")
+        .filter(|t| t.unit_matches("px"))
+        .count();
+    assert_eq!(matching, 4);
+    assert!(tokens[0].unit_matches("pX"));
+    assert!(!tokens[0].unit_matches("em"));
+    assert!(!tokens[6].unit_matches("ä"));
+    assert!(!Token::Ident("px".into()).unit_matches("px"));
+}
+
+#[test]
+fn token_kinds() {
+    let css = "a @b #c #1 'd' url(e) * 1 2% 3px /**/:;,~=|=^=$=*=<!---->f ([{)]}url(g h) 'i\n";
+    let kinds: Vec<_> = Tokens::new(css)
+        .filter(|t| *t != Token::WhiteSpace(" ") && *t != Token::WhiteSpace("\n"))
+        .map(|t| t.kind())
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            TokenKind::Ident,
+            TokenKind::AtKeyword,
+            TokenKind::IDHash,
+            TokenKind::Hash,
+            TokenKind::QuotedString,
+            TokenKind::UnquotedUrl,
+            TokenKind::Delim,
+            TokenKind::Number,
+            TokenKind::Percentage,
+            TokenKind::Dimension,
+            TokenKind::Comment,
+            TokenKind::Colon,
+            TokenKind::Semicolon,
+            TokenKind::Comma,
+            TokenKind::IncludeMatch,
+            TokenKind::DashMatch,
+            TokenKind::PrefixMatch,
+            TokenKind::SuffixMatch,
+            TokenKind::SubstringMatch,
+            TokenKind::CDO,
+            TokenKind::CDC,
+            TokenKind::Ident,
+            TokenKind::ParenthesisBlock,
+            TokenKind::SquareBracketBlock,
+            TokenKind::CurlyBracketBlock,
+            TokenKind::CloseParenthesis,
+            TokenKind::CloseSquareBracket,
+            TokenKind::CloseCurlyBracket,
+            TokenKind::BadUrl,
+            TokenKind::BadString,
+        ]
+    );
+    assert_eq!(Token::WhiteSpace(" ").kind(), TokenKind::WhiteSpace);
+    assert_eq!(Token::Function("f".into()).kind(), TokenKind::Function);
+    assert!(Token::Ident("a".into()).kind() != Token::Function("a".into()).kind());
+}
+
+#[test]
+fn component_value_tree_of_calc() {
+    let mut input = ParserInput::new("calc((1 + 2) * 3)");
+    let mut parser = Parser::new(&mut input);
+    let values = ComponentValue::parse_list(&mut parser);
+
+    fn number<'i>(value: i32) -> ComponentValue<'i> {
+        ComponentValue::Token(Token::Number {
+            has_sign: false,
+            value: value as f32,
+            int_value: Some(value),
+        })
+    }
+    let space = ComponentValue::Token(Token::WhiteSpace(" "));
+    assert_eq!(
+        values,
+        vec![ComponentValue::Function {
+            name: "calc".into(),
+            arguments: vec![
+                ComponentValue::Block {
+                    kind: BlockType::Parenthesis,
+                    contents: vec![
+                        number(1),
+                        space.clone(),
+                        ComponentValue::Token(Token::Delim('+')),
+                        space.clone(),
+                        number(2),
+                    ],
+                },
+                space.clone(),
+                ComponentValue::Token(Token::Delim('*')),
+                space,
+                number(3),
+            ],
+        }]
+    );
+
+    // Missing closing tokens are closed at the end of the input.
+    let mut input = ParserInput::new("calc((1");
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(
+        ComponentValue::parse_list(&mut parser),
+        vec![ComponentValue::Function {
+            name: "calc".into(),
+            arguments: vec![ComponentValue::Block {
+                kind: BlockType::Parenthesis,
+                contents: vec![number(1)],
+            }],
+        }]
+    );
+}
+
+#[test]
+fn numeric_source_representation() {
+    let mut input = ParserInput::new(".5 +5 5E2 0.50% -1e+3px 5e 1e-x 7.em");
+    let mut parser = Parser::new(&mut input);
+    let mut reprs = vec![];
+    while let Ok((token, span)) = parser.next_including_whitespace_and_comments_with_span() {
+        match *token {
+            Token::Number { .. } | Token::Percentage { .. } | Token::Dimension { .. } => {
+                reprs.push(span)
+            }
+            _ => {}
+        }
+    }
+    let reprs: Vec<_> = reprs
+        .into_iter()
+        .map(|span| numeric_repr(parser.slice(span)))
+        .collect();
+    assert_eq!(reprs, [".5", "+5", "5E2", "0.50", "-1e+3", "5", "1", "7"]);
+
+    assert_eq!(numeric_repr("abc"), "");
+    assert_eq!(numeric_repr("-"), "-");
+}
+
+#[test]
+fn hex_color_normalization() {
+    assert_eq!(normalize_hex_color("F00", false), Some("ff0000".into()));
+    assert_eq!(normalize_hex_color("F00", true), Some("f00".into()));
+    assert_eq!(normalize_hex_color("ff0000", true), Some("f00".into()));
+    assert_eq!(normalize_hex_color("FF0000", false), Some("ff0000".into()));
+    assert_eq!(normalize_hex_color("abcd", false), Some("aabbccdd".into()));
+    assert_eq!(normalize_hex_color("AABBCCDD", true), Some("abcd".into()));
+    assert_eq!(normalize_hex_color("ff0001", true), Some("ff0001".into()));
+    assert_eq!(
+        normalize_hex_color("11223345", true),
+        Some("11223345".into())
+    );
+
+    assert_eq!(normalize_hex_color("gg", false), None);
+    assert_eq!(normalize_hex_color("ggg", true), None);
+    assert_eq!(normalize_hex_color("12345", false), None);
+    assert_eq!(normalize_hex_color("", false), None);
+    assert_eq!(normalize_hex_color("éé", false), None);
+
+    // The result describes the same color.
+    for hash in &["F00", "c0ffee", "0f0f", "Abcdef12"] {
+        for &minify in &[false, true] {
+            let normalized = normalize_hex_color(hash, minify).unwrap();
+            assert_eq!(
+                Color::parse_hash(normalized.as_bytes()),
+                Color::parse_hash(hash.as_bytes())
+            );
+        }
+    }
+}
+
+#[test]
+fn numeric_accessors() {
+    fn first<'a>(css: &'a str) -> Token<'a> {
+        Tokens::new(css).next().unwrap()
+    }
+    assert_eq!(first("42").as_i32(), Some(42));
+    assert_eq!(first("-7px").as_i32(), Some(-7));
+    assert_eq!(first("42").as_f32(), Some(42.));
+    assert_eq!(first("300").clamp_i32(0, 255), Some(255));
+    assert_eq!(first("-3").clamp_i32(0, 255), Some(0));
+
+    assert_eq!(first("1.5").as_i32(), None);
+    assert_eq!(first("1.5em").as_f32(), Some(1.5));
+    assert_eq!(first("1.5").clamp_i32(0, 255), None);
+    assert_eq!(first("2e0").as_i32(), None);
+
+    assert_eq!(first("2147483647").as_i32(), Some(2147483647));
+    assert_eq!(first("2147483648").as_i32(), None);
+    assert_eq!(first("-2147483648").as_i32(), Some(-2147483648));
+    assert_eq!(first("-2147483649").as_i32(), None);
+    assert_eq!(first("-2147483700px").as_i32(), None);
+    assert_eq!(first("99999999999").as_i32(), None);
+    assert_eq!(first("-99999999999").as_i32(), None);
+    assert_eq!(first("99999999999").clamp_i32(0, 255), Some(255));
+    assert_eq!(
+        first("99999999999").clamp_i32(-2147483648, 2147483647),
+        Some(2147483647)
+    );
+
+    assert_eq!(first("5%").as_i32(), None);
+    assert_eq!(first("5%").as_f32(), None);
+    assert_eq!(first("a").as_f32(), None);
+}
+
+#[test]
+fn percentage_fractions() {
+    fn fraction(css: &str) -> Option<f32> {
+        Tokens::new(css).next().unwrap().as_percentage()
+    }
+    assert_eq!(fraction("50%"), Some(0.5));
+    assert_eq!(fraction("0%"), Some(0.));
+    assert_eq!(fraction("100%"), Some(1.));
+    assert_eq!(fraction("-25%"), Some(-0.25));
+    assert_eq!(fraction("50"), None);
+    assert_eq!(fraction("50px"), None);
+}
+
+#[test]
+fn operator_tokens() {
+    let operators: Vec<_> = Tokens::new("~= |= ^= $= *= = > + ~ || / ! %")
+        .filter(|t| *t != Token::WhiteSpace(" "))
+        .map(|t| t.as_operator())
+        .collect();
+    assert_eq!(
+        operators,
+        [
+            Some("~="),
+            Some("|="),
+            Some("^="),
+            Some("$="),
+            Some("*="),
+            Some("="),
+            Some(">"),
+            Some("+"),
+            Some("~"),
+            Some("|"),
+            Some("|"),
+            Some("/"),
+            Some("!"),
+            Some("%"),
+        ]
+    );
+    assert_eq!(Token::Delim('\\').as_operator(), Some("\\"));
+    assert_eq!(Token::Delim('"').as_operator(), Some("\""));
+
+    assert_eq!(Token::Ident("a".into()).as_operator(), None);
+    assert_eq!(Token::Colon.as_operator(), None);
+    assert_eq!(Token::Comma.as_operator(), None);
+    assert_eq!(Token::Delim('é').as_operator(), None);
+}
+
+#[test]
+fn color_hash_tokens() {
+    fn parse(css: &str) -> Option<(u8, u8, u8, u8)> {
+        parse_color_hash(&Tokens::new(css).next().unwrap())
+    }
+    assert_eq!(parse("#abc"), Some((0xaa, 0xbb, 0xcc, 0xff)));
+    assert_eq!(parse("#abcd"), Some((0xaa, 0xbb, 0xcc, 0xdd)));
+    assert_eq!(parse("#aabbcc"), Some((0xaa, 0xbb, 0xcc, 0xff)));
+    assert_eq!(parse("#aabbccdd"), Some((0xaa, 0xbb, 0xcc, 0xdd)));
+    assert_eq!(parse("#0F0"), Some((0, 0xff, 0, 0xff)));
+    assert_eq!(parse("#123"), Some((0x11, 0x22, 0x33, 0xff)));
+
+    assert_eq!(parse("#xyz"), None);
+    assert_eq!(parse("#abcde"), None);
+    assert_eq!(parse("#"), None);
+    assert_eq!(parse("abc"), None);
+}
+
+#[test]
+fn parse_color_values() {
+    fn parse(css: &str) -> Result<Color, ()> {
+        let mut input = ParserInput::new(css);
+        let mut parser = Parser::new(&mut input);
+        parser
+            .parse_entirely(|p| Color::parse(p).map_err(ParseError::<()>::from))
+            .map_err(|_| ())
+    }
+    fn rgba(red: u8, green: u8, blue: u8, alpha: u8) -> Result<Color, ()> {
+        Ok(Color::RGBA(RGBA::new(red, green, blue, alpha)))
+    }
+
+    assert_eq!(parse("#f00"), rgba(255, 0, 0, 255));
+    assert_eq!(parse("#F00A"), rgba(255, 0, 0, 170));
+    assert_eq!(parse("#ff000080"), rgba(255, 0, 0, 128));
+    assert_eq!(parse("rgb(255, 0, 0)"), rgba(255, 0, 0, 255));
+    assert_eq!(parse("rgba(0,0,0,.5)"), rgba(0, 0, 0, 128));
+    assert_eq!(parse("hsl(120, 100%, 50%)"), rgba(0, 255, 0, 255));
+    assert_eq!(parse("HSLA(0, 0%, 100%, 0)"), rgba(255, 255, 255, 0));
+    assert_eq!(parse("rebeccapurple"), rgba(102, 51, 153, 255));
+    assert_eq!(parse("transparent"), rgba(0, 0, 0, 0));
+    assert_eq!(parse("currentColor"), Ok(Color::CurrentColor));
+
+    // Out-of-range channels are clamped.
+    assert_eq!(parse("rgb(300, -20, 128)"), rgba(255, 0, 128, 255));
+    assert_eq!(parse("rgba(0, 0, 0, 2)"), rgba(0, 0, 0, 255));
+    assert_eq!(parse("rgb(150%, 0%, 0%)"), rgba(255, 0, 0, 255));
+
+    // Invalid syntax is an error, not a panic.
+    for css in &[
+        "#gg0",
+        "#12345",
+        "rgb(255, 0)",
+        "rgb(255 0 0 0)",
+        "rgb(1, 2%, 3)",
+        "hsl(a, 0%, 0%)",
+        "notacolor",
+        "rgb(",
+        "\"red\"",
+    ] {
+        assert_eq!(parse(css), Err(()), "{}", css);
+    }
+}
+
+#[test]
+fn null_replacement_config() {
+    let css = "a\0b \"c\0\\0\" url(d\0) \\000000e";
+    assert_eq!(
+        Tokens::new(css)
+            .filter(|t| *t != Token::WhiteSpace(" "))
+            .collect::<Vec<_>>(),
+        vec![
+            Token::Ident("a\u{FFFD}b".into()),
+            Token::QuotedString("c\u{FFFD}\u{FFFD}".into()),
+            Token::UnquotedUrl("d\u{FFFD}".into()),
+            Token::Ident("\u{FFFD}e".into()),
+        ]
+    );
+    assert_eq!(Tokens::new(css).errors(), &[]);
+
+    let config = TokenizerConfig {
+        replacement_char: '?',
+        error_on_null: true,
+        ..TokenizerConfig::default()
+    };
+    let mut tokens = Tokens::with_config(css, config);
+    assert_eq!(
+        tokens
+            .by_ref()
+            .filter(|t| *t != Token::WhiteSpace(" "))
+            .collect::<Vec<_>>(),
+        vec![
+            Token::Ident("a?b".into()),
+            Token::QuotedString("c??".into()),
+            Token::UnquotedUrl("d?".into()),
+            Token::Ident("?e".into()),
+        ]
+    );
+    let columns: Vec<_> = tokens
+        .errors()
+        .iter()
+        .map(|error| {
+            assert_eq!(error.kind, TokenizerErrorKind::NullCharacter);
+            error.location.column
+        })
+        .collect();
+    assert_eq!(columns, [2, 7, 9, 17, 21]);
+
+    // Other invalid escapes still become U+FFFD.
+    let mut input = ParserInput::new_with_config("\\110000 \\D800", config);
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.expect_ident_cloned(), Ok("\u{FFFD}\u{FFFD}".into()));
+    assert_eq!(parser.tokenizer_errors(), &[]);
+}
+
+#[test]
+fn max_token_len() {
+    let config = TokenizerConfig {
+        max_token_len: Some(4),
+        ..TokenizerConfig::default()
+    };
+    let css = "'abcdefgh' abcd url(abcdefgh) 5abcdefgh \\61 bcdef 'é€é' \"abcde\nx";
+    let mut tokens = Tokens::with_config(css, config);
+    assert_eq!(
+        tokens
+            .by_ref()
+            .filter(|t| *t != Token::WhiteSpace(" "))
+            .collect::<Vec<_>>(),
+        vec![
+            Token::QuotedString("abcd".into()),
+            Token::Ident("abcd".into()),
+            Token::UnquotedUrl("abcd".into()),
+            Token::Dimension {
+                has_sign: false,
+                value: 5.,
+                int_value: Some(5),
+                unit: "abcd".into(),
+            },
+            Token::Ident("abcd".into()),
+            Token::QuotedString("é".into()),
+            Token::BadString("abcd".into()),
+            Token::WhiteSpace("\n"),
+            Token::Ident("x".into()),
+        ]
+    );
+    let errors: Vec<_> = tokens
+        .errors()
+        .iter()
+        .map(|error| (error.kind, error.location.column))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (TokenizerErrorKind::TokenTooLong, 1),
+            (TokenizerErrorKind::TokenTooLong, 17),
+            (TokenizerErrorKind::TokenTooLong, 31),
+            (TokenizerErrorKind::TokenTooLong, 41),
+            (TokenizerErrorKind::TokenTooLong, 51),
+            (TokenizerErrorKind::TokenTooLong, 57),
+            (TokenizerErrorKind::BadString, 63),
+        ]
+    );
+
+    let tokens: Vec<_> = Tokens::new(css).collect();
+    assert_eq!(tokens[0], Token::QuotedString("abcdefgh".into()));
+
+    // Values with escapes are truncated the same way, at a code point boundary.
+    let long = "\\e9 ".repeat(10_000);
+    let css = format!("'{}' {} url({}) 5{}", long, long, long, long);
+    let prefixed = format!("{}\\e9", "é".repeat(10_000));
+    let mut tokens = Tokens::with_config(&prefixed, config);
+    assert_eq!(tokens.next(), Some(Token::Ident("éé".into())));
+    let tokens: Vec<_> = Tokens::with_config(&css, config)
+        .filter(|t| *t != Token::WhiteSpace(" "))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::QuotedString("éé".into()),
+            Token::Ident("éé".into()),
+            Token::UnquotedUrl("éé".into()),
+            Token::Dimension {
+                has_sign: false,
+                value: 5.,
+                int_value: Some(5),
+                unit: "éé".into(),
+            },
+        ]
+    );
+    let mut tokens = Tokens::with_config(&css, config);
+    while tokens.next().is_some() {}
+    assert_eq!(tokens.errors().len(), 4);
+}
+
+#[test]
+fn form_feed_is_a_newline() {
+    fn tokens<'a>(css: &'a str) -> Vec<Token<'a>> {
+        Tokens::new(css).collect()
+    }
+    assert_eq!(tokens("url(\x0Ca\x0C)"), [Token::UnquotedUrl("a".into())]);
+    assert_eq!(
+        tokens("a:b\x0Cc"),
+        [
+            Token::Ident("a".into()),
+            Token::Colon,
+            Token::Ident("b".into()),
+            Token::WhiteSpace("\x0C"),
+            Token::Ident("c".into()),
+        ]
+    );
+    assert_eq!(tokens("'a\\\x0Cb'"), [Token::QuotedString("ab".into())]);
+    assert_eq!(
+        tokens("'a\x0Cb'"),
+        [
+            Token::BadString("a".into()),
+            Token::WhiteSpace("\x0C"),
+            Token::Ident("b".into()),
+            Token::QuotedString("".into()),
+        ]
+    );
+    assert_eq!(
+        tokens("a\\\x0C"),
+        [
+            Token::Ident("a".into()),
+            Token::Delim('\\'),
+            Token::WhiteSpace("\x0C"),
+        ]
+    );
+
+    // Each form feed starts a new line.
+    for css in &["url(\x0Ca\x0C)", "a:b\x0Cc", "'a\\\x0Cb'", "a\\\x0C"] {
+        let mut tokens = Tokens::new(css);
+        while tokens.next().is_some() {}
+        let expected = css.matches('\x0C').count() as u32;
+        assert_eq!(tokens.current_source_location().line, expected, "{:?}", css);
+    }
+}
+
+#[test]
+fn block_tokens() {
+    let openers = [
+        (Token::Function("f".into()), BlockType::Parenthesis),
+        (Token::ParenthesisBlock, BlockType::Parenthesis),
+        (Token::SquareBracketBlock, BlockType::SquareBracket),
+        (Token::CurlyBracketBlock, BlockType::CurlyBracket),
+    ];
+    for &(ref opener, block_type) in &openers {
+        assert_eq!(opener.block_start(), Some(block_type));
+        assert_eq!(opener.block_end(), None);
+        assert!(!opener.is_preserved());
+        let closer = block_type.closing_token();
+        assert_eq!(closer.block_end(), Some(block_type));
+        assert_eq!(closer.block_start(), None);
+        assert!(closer.is_preserved());
+    }
+    assert_eq!(
+        Token::CloseCurlyBracket.block_end(),
+        Some(BlockType::CurlyBracket)
+    );
+    assert_eq!(
+        BlockType::SquareBracket.closing_token(),
+        Token::CloseSquareBracket
+    );
+
+    let ident = Token::Ident("a".into());
+    assert_eq!(ident.block_start(), None);
+    assert_eq!(ident.block_end(), None);
+    assert!(ident.is_preserved());
+}
+
+#[test]
+fn columns_with_tab_width() {
+    let css = "a {\n\tb: c;\n\t\td:\te;\n  \tf: g }";
+    let mut tokens = Tokens::new(css);
+    let mut columns = Vec::new();
+    while let Some(token) = tokens.next() {
+        if let Token::Ident(_) = token {
+            let location = tokens.current_source_location();
+            columns.push((
+                location.line,
+                location.column,
+                tokens.current_column_with_tab_width(1),
+                tokens.current_column_with_tab_width(4),
+                tokens.current_column_with_tab_width(8),
+            ));
+        }
+    }
+    assert_eq!(
+        columns,
+        [
+            (0, 2, 2, 2, 2),
+            (1, 3, 3, 6, 10),
+            (1, 6, 6, 9, 13),
+            (2, 4, 4, 10, 18),
+            (2, 7, 7, 14, 26),
+            (3, 5, 5, 6, 10),
+            (3, 8, 8, 9, 13),
+        ]
+    );
+
+    let mut input = ParserInput::new("\u{FEFF}\ta");
+    let mut parser = Parser::new(&mut input);
+    parser.expect_ident().unwrap();
+    assert_eq!(parser.current_source_location().column, 3);
+    assert_eq!(parser.current_column_with_tab_width(4), 6);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn tokens_serialized_with_serde() {
+    let tokens: Vec<_> = Tokens::new("a:1.5px 50% /**/ 'b'{#c}*").collect();
+    assert_eq!(
+        serde_json::to_value(&tokens).unwrap(),
+        json!([
+            {"Ident": "a"},
+            "Colon",
+            {"Dimension": {"has_sign": false, "value": 1.5, "int_value": null, "unit": "px"}},
+            {"WhiteSpace": " "},
+            {"Percentage": {"has_sign": false, "unit_value": 0.5, "int_value": 50}},
+            {"WhiteSpace": " "},
+            {"Comment": ""},
+            {"WhiteSpace": " "},
+            {"QuotedString": "b"},
+            "CurlyBracketBlock",
+            {"IDHash": "c"},
+            "CloseCurlyBracket",
+            {"Delim": "*"},
+        ])
+    );
+    assert_eq!(
+        serde_json::to_string(&Token::Number {
+            has_sign: true,
+            value: -2.,
+            int_value: Some(-2),
+        })
+        .unwrap(),
+        r#"{"Number":{"has_sign":true,"value":-2.0,"int_value":-2}}"#
+    );
+}
+
+#[test]
+fn lowercase_identifiers() {
+    let css = "DIV @MEDIA RGB( 10PX #ABC 'Keep' url(A.PNG) url('B') --Custom";
+    let config = TokenizerConfig {
+        lowercase_identifiers: true,
+        ..TokenizerConfig::default()
+    };
+    let tokens: Vec<_> = Tokens::with_config(css, config)
+        .filter(|t| *t != Token::WhiteSpace(" "))
+        .collect();
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Ident("div".into()),
+            Token::AtKeyword("media".into()),
+            Token::Function("rgb".into()),
+            Token::Dimension {
+                has_sign: false,
+                value: 10.,
+                int_value: Some(10),
+                unit: "px".into(),
+            },
+            Token::IDHash("ABC".into()),
+            Token::QuotedString("Keep".into()),
+            Token::UnquotedUrl("A.PNG".into()),
+            Token::Function("url".into()),
+            Token::QuotedString("B".into()),
+            Token::CloseParenthesis,
+            Token::Ident("--Custom".into()),
+        ]
+    );
+
+    let mut tokens = Tokens::new(css);
+    assert_eq!(tokens.next(), Some(Token::Ident("DIV".into())));
+
+    let mut input = ParserInput::new_with_config("DIV", config);
+    let mut parser = Parser::new(&mut input);
+    assert_eq!(parser.expect_ident_cloned(), Ok("div".into()));
+}
+
+#[test]
+fn consume_until_after() {
+    let mut tokens = Tokens::new("color: ); ; width: 1px");
+    tokens.consume_until_after(&[TokenKind::Semicolon]);
+    assert_eq!(tokens.remaining(), " ; width: 1px");
+    tokens.consume_until_after(&[TokenKind::Semicolon]);
+    assert_eq!(tokens.remaining(), " width: 1px");
+
+    let mut tokens = Tokens::new("a: f(;) [;] {;}; b; c");
+    tokens.consume_until_after(&[TokenKind::Semicolon]);
+    assert_eq!(tokens.remaining(), " b; c");
+
+    let mut tokens = Tokens::new("a: (; } b");
+    tokens.consume_until_after(&[TokenKind::Semicolon, TokenKind::CloseCurlyBracket]);
+    assert_eq!(tokens.remaining(), "");
+
+    let mut tokens = Tokens::new("a: 1 } b");
+    tokens.consume_until_after(&[TokenKind::Semicolon, TokenKind::CloseCurlyBracket]);
+    assert_eq!(tokens.next(), Some(Token::WhiteSpace(" ")));
+    assert_eq!(tokens.next(), Some(Token::Ident("b".into())));
+}
+
+#[test]
+fn unicode_range_chars() {
+    let range = UnicodeRange {
+        start: 0x41,
+        end: 0x5A,
+    };
+    assert!(range.contains('A'));
+    assert!(range.contains('M'));
+    assert!(range.contains('Z'));
+    assert!(!range.contains('a'));
+    assert!(!range.contains('@'));
+    assert_eq!(range.to_char_range(), Some('A'..='Z'));
+
+    let range = UnicodeRange {
+        start: 0xD800,
+        end: 0xDFFF,
+    };
+    assert!(!range.contains('\u{FFFD}'));
+    assert_eq!(range.to_char_range(), None);
+
+    let range = UnicodeRange {
+        start: 0,
+        end: 0x11_0000,
+    };
+    assert_eq!(range.to_char_range(), None);
+}
+
+#[test]
+fn numeric_signs() {
+    fn signs(css: &str) -> (bool, bool) {
+        let token = Tokens::new(css).next().unwrap();
+        (token.has_sign(), token.is_negative())
+    }
+    assert_eq!(signs("+5"), (true, false));
+    assert_eq!(signs("-5"), (true, true));
+    assert_eq!(signs("5"), (false, false));
+    assert_eq!(signs("+.5"), (true, false));
+    assert_eq!(signs("-0"), (true, true));
+    assert_eq!(signs("+5%"), (true, false));
+    assert_eq!(signs("-5px"), (true, true));
+    assert_eq!(signs("5px"), (false, false));
+    assert_eq!(signs("n"), (false, false));
+
+    assert_eq!(
+        Tokens::new("+.5").next(),
+        Some(Token::Number {
+            has_sign: true,
+            value: 0.5,
+            int_value: None,
+        })
+    );
+    assert_eq!(
+        Token::Number {
+            has_sign: true,
+            value: 5.,
+            int_value: Some(5),
+        }
+        .to_css_string(),
+        "+5"
+    );
+}
@@ -4,13 +4,18 @@
 
 // https://drafts.csswg.org/css-syntax/#tokenization
 
+use std::borrow::Cow;
 use std::char;
 use std::i32;
 use std::ops::Range;
 
 use self::Token::*;
 use cow_rc_str::CowRcStr;
-use parser::ParserState;
+use parser::{consume_until_end_of_block, BlockType, ParserState};
+use serializer::TokenSerializationType;
+
+#[cfg(feature = "serde")]
+use serde::ser::{Serialize, SerializeStructVariant, Serializer};
 
 /// One of the pieces the CSS input is broken into.
 ///
@@ -58,9 +63,14 @@ pub enum Token<'a> {
         has_sign: bool,
 
         /// The value as a float
+        ///
+        /// This is not exact for values like `0.1`. The digits as written in the source
+        /// can be recovered with `Parser::next_including_whitespace_and_comments_with_span`.
+        /// Values too large for `f32` are infinite.
         value: f32,
 
-        /// If the origin source did not include a fractional part, the value as an integer.
+        /// If the origin source did not include a fractional part, the value as an integer,
+        /// clamped to the range of `i32`.
         int_value: Option<i32>,
     },
 
@@ -72,7 +82,8 @@ pub enum Token<'a> {
         /// The value as a float, divided by 100 so that the nominal range is 0.0 to 1.0.
         unit_value: f32,
 
-        /// If the origin source did not include a fractional part, the value as an integer.
+        /// If the origin source did not include a fractional part, the value as an integer,
+        /// clamped to the range of `i32`.
         /// It is **not** divided by 100.
         int_value: Option<i32>,
     },
@@ -87,7 +98,8 @@ pub enum Token<'a> {
         /// The value as a float
         value: f32,
 
-        /// If the origin source did not include a fractional part, the value as an integer.
+        /// If the origin source did not include a fractional part, the value as an integer,
+        /// clamped to the range of `i32`.
         int_value: Option<i32>,
 
         /// The unit, e.g. "px" in `12px`
@@ -103,6 +115,9 @@ pub enum Token<'a> {
     /// But we do, because we can (borrowed &str makes it cheap).
     ///
     /// The value does not include the `/*` `*/` markers.
+    /// A comment left unterminated at the end of the input contains the rest of the input.
+    ///
+    /// Comments are only returned by `Parser::next_including_whitespace_and_comments`.
     Comment(&'a str),
 
     /// A `:` `<colon-token>`
@@ -152,11 +167,16 @@ pub enum Token<'a> {
     /// A `<bad-url-token>`
     ///
     /// This token always indicates a parse error.
+    ///
+    /// The value is the source after `url(`, escapes included,
+    /// up to the closing `)` or the end of the input.
     BadUrl(CowRcStr<'a>),
 
     /// A `<bad-string-token>`
     ///
     /// This token always indicates a parse error.
+    ///
+    /// The value is the unescaped string up to the newline that ended it.
     BadString(CowRcStr<'a>),
 
     /// A `<)-token>`
@@ -191,6 +211,468 @@ impl<'a> Token<'a> {
             BadUrl(_) | BadString(_) | CloseParenthesis | CloseSquareBracket | CloseCurlyBracket
         )
     }
+
+    /// Return the kind of block that this token starts:
+    /// a `Function` or `ParenthesisBlock` starts a `BlockType::Parenthesis` block.
+    pub fn block_start(&self) -> Option<BlockType> {
+        match *self {
+            Function(_) | ParenthesisBlock => Some(BlockType::Parenthesis),
+            SquareBracketBlock => Some(BlockType::SquareBracket),
+            CurlyBracketBlock => Some(BlockType::CurlyBracket),
+            _ => None,
+        }
+    }
+
+    /// Return the kind of block that this token ends.
+    pub fn block_end(&self) -> Option<BlockType> {
+        match *self {
+            CloseParenthesis => Some(BlockType::Parenthesis),
+            CloseSquareBracket => Some(BlockType::SquareBracket),
+            CloseCurlyBracket => Some(BlockType::CurlyBracket),
+            _ => None,
+        }
+    }
+
+    /// Return whether this is a preserved token, one that does not start a block.
+    ///
+    /// https://drafts.csswg.org/css-syntax/#preserved-tokens
+    pub fn is_preserved(&self) -> bool {
+        self.block_start().is_none()
+    }
+
+    /// Return whether this is an `Ident` token with the given value,
+    /// compared case-insensitively in the ASCII range.
+    pub fn is_ident(&self, name: &str) -> bool {
+        match *self {
+            Ident(ref value) => value.eq_ignore_ascii_case(name),
+            _ => false,
+        }
+    }
+
+    /// Return whether this is an `Ident` token that names a custom property,
+    /// such as `--main-color`.
+    pub fn is_custom_property_name(&self) -> bool {
+        match *self {
+            Ident(ref value) => value.starts_with("--"),
+            _ => false,
+        }
+    }
+
+    /// Return whether this is a `Function` token with the given name,
+    /// compared case-insensitively in the ASCII range.
+    pub fn is_function(&self, name: &str) -> bool {
+        match *self {
+            Function(ref value) => value.eq_ignore_ascii_case(name),
+            _ => false,
+        }
+    }
+
+    /// Return whether this is an `AtKeyword` token with the given name,
+    /// compared case-insensitively in the ASCII range.
+    pub fn is_at_keyword(&self, name: &str) -> bool {
+        match *self {
+            AtKeyword(ref value) => value.eq_ignore_ascii_case(name),
+            _ => false,
+        }
+    }
+
+    /// Return the name of an `AtKeyword` token, as written.
+    ///
+    /// Compare it with `eq_ignore_ascii_case` or `match_ignore_ascii_case!`,
+    /// since at-rule names are ASCII case-insensitive.
+    pub fn at_keyword(&self) -> Option<&str> {
+        match *self {
+            AtKeyword(ref name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Return the text of a match operator such as `~=`,
+    /// or of a `Delim` token holding ASCII punctuation such as `>` or `=`.
+    ///
+    /// Return `None` for other tokens, including `Colon`, `Semicolon` and `Comma`.
+    /// There is no column token: `||` is two `Delim('|')` tokens.
+    pub fn as_operator(&self) -> Option<&'static str> {
+        const PUNCTUATION: &str = "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+        match *self {
+            IncludeMatch => Some("~="),
+            DashMatch => Some("|="),
+            PrefixMatch => Some("^="),
+            SuffixMatch => Some("$="),
+            SubstringMatch => Some("*="),
+            Delim(c) if c.is_ascii_punctuation() => {
+                let i = PUNCTUATION.find(c)?;
+                Some(&PUNCTUATION[i..i + 1])
+            }
+            _ => None,
+        }
+    }
+
+    /// Return the unit of a `Dimension` token, as written
+    /// (after escapes are decoded), such as `PX` in `10PX`.
+    pub fn dimension_unit(&self) -> Option<&str> {
+        match *self {
+            Dimension { ref unit, .. } => Some(unit),
+            _ => None,
+        }
+    }
+
+    /// Return whether this is a `Dimension` token with the given unit,
+    /// compared case-insensitively in the ASCII range.
+    pub fn unit_matches(&self, unit: &str) -> bool {
+        match *self {
+            Dimension { unit: ref u, .. } => u.eq_ignore_ascii_case(unit),
+            _ => false,
+        }
+    }
+
+    /// Return the unit of a `Dimension` token, lowercased in the ASCII range,
+    /// so that units can be compared as the case-insensitive keywords they are.
+    /// Only allocates if the unit has ASCII uppercase letters.
+    pub fn dimension_unit_lowercase<'s>(&'s self) -> Option<Cow<'s, str>> {
+        self.dimension_unit().map(|unit| {
+            if unit.bytes().any(|b| b.is_ascii_uppercase()) {
+                Cow::Owned(unit.to_ascii_lowercase())
+            } else {
+                Cow::Borrowed(unit)
+            }
+        })
+    }
+
+    /// Return the numeric value of a `Number` or `Dimension` token.
+    pub fn as_f32(&self) -> Option<f32> {
+        match *self {
+            Number { value, .. } | Dimension { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Return the value of a `Percentage` token as a fraction, such as 0.5 for `50%`.
+    /// This is the token's `unit_value`.
+    pub fn as_percentage(&self) -> Option<f32> {
+        match *self {
+            Percentage { unit_value, .. } => Some(unit_value),
+            _ => None,
+        }
+    }
+
+    /// Return the value of a `Number` or `Dimension` token written as an integer,
+    /// if it is in the range of `i32`.
+    pub fn as_i32(&self) -> Option<i32> {
+        match *self {
+            Number {
+                value,
+                int_value: Some(int_value),
+                ..
+            }
+            | Dimension {
+                value,
+                int_value: Some(int_value),
+                ..
+            } if value >= i32::MIN as f32 && value < i32::MAX as f32 => Some(int_value),
+            _ => None,
+        }
+    }
+
+    /// Return the value of a `Number` or `Dimension` token written as an integer,
+    /// clamped to the range from `min` to `max` inclusive.
+    /// Integers out of the range of `i32` are clamped too.
+    pub fn clamp_i32(&self, min: i32, max: i32) -> Option<i32> {
+        match *self {
+            Number {
+                int_value: Some(int_value),
+                ..
+            }
+            | Dimension {
+                int_value: Some(int_value),
+                ..
+            } => Some(int_value.max(min).min(max)),
+            _ => None,
+        }
+    }
+
+    /// Return whether a `Number`, `Percentage` or `Dimension` token was written
+    /// with a `+` or `-` sign, as in `+5` or `-5` but not `5`.
+    /// This is the token's `has_sign`.
+    /// A token is explicitly positive, as in `+5`, if this is true and `is_negative` is false.
+    ///
+    /// Serialization keeps an explicit `+`: `+5` is written back as `+5`.
+    pub fn has_sign(&self) -> bool {
+        match *self {
+            Number { has_sign, .. } | Percentage { has_sign, .. } | Dimension { has_sign, .. } => {
+                has_sign
+            }
+            _ => false,
+        }
+    }
+
+    /// Return whether a `Number`, `Percentage` or `Dimension` token was written with a `-` sign.
+    /// This includes `-0`, whose value is negative zero.
+    pub fn is_negative(&self) -> bool {
+        match *self {
+            Number { value, .. }
+            | Percentage {
+                unit_value: value, ..
+            }
+            | Dimension { value, .. } => value.is_sign_negative(),
+            _ => false,
+        }
+    }
+
+    /// Return a `Comment` token as written in `source`, with its `/*` and `*/` delimiters.
+    /// The closing delimiter is missing if the comment is unterminated.
+    ///
+    /// Return `None` for other tokens,
+    /// or if the token was not tokenized from `source`.
+    pub fn comment_source<'s>(&self, source: &'s str) -> Option<&'s str> {
+        let text = match *self {
+            Comment(text) => text,
+            _ => return None,
+        };
+        let text_start = (text.as_ptr() as usize).wrapping_sub(source.as_ptr() as usize);
+        let text_end = text_start.wrapping_add(text.len());
+        let bytes = source.as_bytes();
+        if text_start < 2
+            || text_end < text_start
+            || text_end > bytes.len()
+            || &bytes[text_start - 2..text_start] != b"/*"
+        {
+            return None;
+        }
+        let end = if bytes[text_end..].starts_with(b"*/") {
+            text_end + 2
+        } else {
+            text_end
+        };
+        source.get(text_start - 2..end)
+    }
+
+    /// Return the kind of this token, without its value.
+    pub fn kind(&self) -> TokenKind {
+        match *self {
+            Ident(_) => TokenKind::Ident,
+            AtKeyword(_) => TokenKind::AtKeyword,
+            Hash(_) => TokenKind::Hash,
+            IDHash(_) => TokenKind::IDHash,
+            QuotedString(_) => TokenKind::QuotedString,
+            UnquotedUrl(_) => TokenKind::UnquotedUrl,
+            Delim(_) => TokenKind::Delim,
+            Number { .. } => TokenKind::Number,
+            Percentage { .. } => TokenKind::Percentage,
+            Dimension { .. } => TokenKind::Dimension,
+            WhiteSpace(_) => TokenKind::WhiteSpace,
+            Comment(_) => TokenKind::Comment,
+            Colon => TokenKind::Colon,
+            Semicolon => TokenKind::Semicolon,
+            Comma => TokenKind::Comma,
+            IncludeMatch => TokenKind::IncludeMatch,
+            DashMatch => TokenKind::DashMatch,
+            PrefixMatch => TokenKind::PrefixMatch,
+            SuffixMatch => TokenKind::SuffixMatch,
+            SubstringMatch => TokenKind::SubstringMatch,
+            CDO => TokenKind::CDO,
+            CDC => TokenKind::CDC,
+            Function(_) => TokenKind::Function,
+            ParenthesisBlock => TokenKind::ParenthesisBlock,
+            SquareBracketBlock => TokenKind::SquareBracketBlock,
+            CurlyBracketBlock => TokenKind::CurlyBracketBlock,
+            BadUrl(_) => TokenKind::BadUrl,
+            BadString(_) => TokenKind::BadString,
+            CloseParenthesis => TokenKind::CloseParenthesis,
+            CloseSquareBracket => TokenKind::CloseSquareBracket,
+            CloseCurlyBracket => TokenKind::CloseCurlyBracket,
+        }
+    }
+}
+
+/// Tokens serialize as serde's externally tagged enums,
+/// with the variant and field names of `Token`:
+/// `"Colon"`, `{"Ident":"a"}`, `{"Delim":"*"}` or
+/// `{"Dimension":{"has_sign":false,"value":1.5,"int_value":null,"unit":"px"}}`.
+///
+/// Numbers are serialized as `f32`.
+/// Numbers too large for `f32` tokenize to infinity,
+/// which formats like `serde_json` write as `null`. NaN never occurs.
+///
+/// There is no `Deserialize` implementation, since `WhiteSpace` and `Comment`
+/// can only borrow their value from the input.
+#[cfg(feature = "serde")]
+impl<'a> Serialize for Token<'a> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // `TokenKind` has the same variants in the same order.
+        let index = self.kind() as u32;
+        match *self {
+            Ident(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "Ident", &**value)
+            }
+            AtKeyword(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "AtKeyword", &**value)
+            }
+            Hash(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "Hash", &**value)
+            }
+            IDHash(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "IDHash", &**value)
+            }
+            QuotedString(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "QuotedString", &**value)
+            }
+            UnquotedUrl(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "UnquotedUrl", &**value)
+            }
+            Delim(value) => serializer.serialize_newtype_variant("Token", index, "Delim", &value),
+            Number {
+                has_sign,
+                value,
+                int_value,
+            } => {
+                let mut s = serializer.serialize_struct_variant("Token", index, "Number", 3)?;
+                s.serialize_field("has_sign", &has_sign)?;
+                s.serialize_field("value", &value)?;
+                s.serialize_field("int_value", &int_value)?;
+                s.end()
+            }
+            Percentage {
+                has_sign,
+                unit_value,
+                int_value,
+            } => {
+                let mut s = serializer.serialize_struct_variant("Token", index, "Percentage", 3)?;
+                s.serialize_field("has_sign", &has_sign)?;
+                s.serialize_field("unit_value", &unit_value)?;
+                s.serialize_field("int_value", &int_value)?;
+                s.end()
+            }
+            Dimension {
+                has_sign,
+                value,
+                int_value,
+                ref unit,
+            } => {
+                let mut s = serializer.serialize_struct_variant("Token", index, "Dimension", 4)?;
+                s.serialize_field("has_sign", &has_sign)?;
+                s.serialize_field("value", &value)?;
+                s.serialize_field("int_value", &int_value)?;
+                s.serialize_field("unit", &**unit)?;
+                s.end()
+            }
+            WhiteSpace(value) => {
+                serializer.serialize_newtype_variant("Token", index, "WhiteSpace", value)
+            }
+            Comment(value) => {
+                serializer.serialize_newtype_variant("Token", index, "Comment", value)
+            }
+            Function(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "Function", &**value)
+            }
+            BadUrl(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "BadUrl", &**value)
+            }
+            BadString(ref value) => {
+                serializer.serialize_newtype_variant("Token", index, "BadString", &**value)
+            }
+            Colon => serializer.serialize_unit_variant("Token", index, "Colon"),
+            Semicolon => serializer.serialize_unit_variant("Token", index, "Semicolon"),
+            Comma => serializer.serialize_unit_variant("Token", index, "Comma"),
+            IncludeMatch => serializer.serialize_unit_variant("Token", index, "IncludeMatch"),
+            DashMatch => serializer.serialize_unit_variant("Token", index, "DashMatch"),
+            PrefixMatch => serializer.serialize_unit_variant("Token", index, "PrefixMatch"),
+            SuffixMatch => serializer.serialize_unit_variant("Token", index, "SuffixMatch"),
+            SubstringMatch => serializer.serialize_unit_variant("Token", index, "SubstringMatch"),
+            CDO => serializer.serialize_unit_variant("Token", index, "CDO"),
+            CDC => serializer.serialize_unit_variant("Token", index, "CDC"),
+            ParenthesisBlock => {
+                serializer.serialize_unit_variant("Token", index, "ParenthesisBlock")
+            }
+            SquareBracketBlock => {
+                serializer.serialize_unit_variant("Token", index, "SquareBracketBlock")
+            }
+            CurlyBracketBlock => {
+                serializer.serialize_unit_variant("Token", index, "CurlyBracketBlock")
+            }
+            CloseParenthesis => {
+                serializer.serialize_unit_variant("Token", index, "CloseParenthesis")
+            }
+            CloseSquareBracket => {
+                serializer.serialize_unit_variant("Token", index, "CloseSquareBracket")
+            }
+            CloseCurlyBracket => {
+                serializer.serialize_unit_variant("Token", index, "CloseCurlyBracket")
+            }
+        }
+    }
+}
+
+/// The kind of a `Token`, without its value.
+///
+/// This makes it easy to compare tokens by kind, or to collect the kinds of expected tokens.
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum TokenKind {
+    /// `Token::Ident`
+    Ident,
+    /// `Token::AtKeyword`
+    AtKeyword,
+    /// `Token::Hash`
+    Hash,
+    /// `Token::IDHash`
+    IDHash,
+    /// `Token::QuotedString`
+    QuotedString,
+    /// `Token::UnquotedUrl`
+    UnquotedUrl,
+    /// `Token::Delim`
+    Delim,
+    /// `Token::Number`
+    Number,
+    /// `Token::Percentage`
+    Percentage,
+    /// `Token::Dimension`
+    Dimension,
+    /// `Token::WhiteSpace`
+    WhiteSpace,
+    /// `Token::Comment`
+    Comment,
+    /// `Token::Colon`
+    Colon,
+    /// `Token::Semicolon`
+    Semicolon,
+    /// `Token::Comma`
+    Comma,
+    /// `Token::IncludeMatch`
+    IncludeMatch,
+    /// `Token::DashMatch`
+    DashMatch,
+    /// `Token::PrefixMatch`
+    PrefixMatch,
+    /// `Token::SuffixMatch`
+    SuffixMatch,
+    /// `Token::SubstringMatch`
+    SubstringMatch,
+    /// `Token::CDO`
+    CDO,
+    /// `Token::CDC`
+    CDC,
+    /// `Token::Function`
+    Function,
+    /// `Token::ParenthesisBlock`
+    ParenthesisBlock,
+    /// `Token::SquareBracketBlock`
+    SquareBracketBlock,
+    /// `Token::CurlyBracketBlock`
+    CurlyBracketBlock,
+    /// `Token::BadUrl`
+    BadUrl,
+    /// `Token::BadString`
+    BadString,
+    /// `Token::CloseParenthesis`
+    CloseParenthesis,
+    /// `Token::CloseSquareBracket`
+    CloseSquareBracket,
+    /// `Token::CloseCurlyBracket`
+    CloseCurlyBracket,
 }
 
 #[derive(Clone)]
@@ -206,6 +688,8 @@ pub struct Tokenizer<'a> {
     var_or_env_functions: SeenStatus,
     source_map_url: Option<&'a str>,
     source_url: Option<&'a str>,
+    errors: Vec<TokenizerError>,
+    config: TokenizerConfig,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -223,14 +707,32 @@ impl<'a> Tokenizer<'a> {
 
     #[inline]
     pub fn with_first_line_number(input: &str, first_line_number: u32) -> Tokenizer {
+        Tokenizer::with_config(input, first_line_number, TokenizerConfig::default())
+    }
+
+    #[inline]
+    pub fn with_config(
+        input: &'a str,
+        first_line_number: u32,
+        config: TokenizerConfig,
+    ) -> Tokenizer<'a> {
+        // A leading byte order mark is not part of the stylesheet.
+        // Columns on the first line are counted from after it.
+        let start = if config.skip_byte_order_mark && input.starts_with('\u{FEFF}') {
+            '\u{FEFF}'.len_utf8()
+        } else {
+            0
+        };
         Tokenizer {
             input: input,
-            position: 0,
-            current_line_start_position: 0,
+            position: start,
+            current_line_start_position: start,
             current_line_number: first_line_number,
             var_or_env_functions: SeenStatus::DontCare,
             source_map_url: None,
             source_url: None,
+            errors: Vec::new(),
+            config,
         }
     }
 
@@ -257,7 +759,23 @@ impl<'a> Tokenizer<'a> {
 
     #[inline]
     pub fn next(&mut self) -> Result<Token<'a>, ()> {
-        next_token(self)
+        if self.config.max_token_len.is_none() && !self.config.lowercase_identifiers {
+            return next_token(self);
+        }
+        let location = self.current_source_location();
+        let error_count = self.errors.len();
+        let mut token = next_token(self)?;
+        if self.config.lowercase_identifiers {
+            lowercase_identifier(&mut token);
+        }
+        let max_len = match self.config.max_token_len {
+            Some(max_len) => max_len,
+            None => return Ok(token),
+        };
+        if truncate_token_value(&mut token, max_len) {
+            self.report_error_before(error_count, TokenizerErrorKind::TokenTooLong, location);
+        }
+        Ok(token)
     }
 
     #[inline]
@@ -283,6 +801,91 @@ impl<'a> Tokenizer<'a> {
         self.source_url
     }
 
+    #[inline]
+    pub fn errors(&self) -> &[TokenizerError] {
+        &self.errors
+    }
+
+    fn report_error(&mut self, kind: TokenizerErrorKind, location: SourceLocation) {
+        // After `reset`, the same input can be tokenized again.
+        // Errors are found in source order, so only record new ones.
+        let is_new = match self.errors.last() {
+            Some(last) => {
+                (last.location.line, last.location.column) < (location.line, location.column)
+            }
+            None => true,
+        };
+        if is_new {
+            self.errors.push(TokenizerError { kind, location })
+        }
+    }
+
+    // Report an error found after the errors from `error_count` on, but located before them,
+    // such as at the start of a token that contains other errors.
+    fn report_error_before(
+        &mut self,
+        error_count: usize,
+        kind: TokenizerErrorKind,
+        location: SourceLocation,
+    ) {
+        // Keep errors in source order.
+        let errors_after = self.errors.split_off(error_count);
+        self.report_error(kind, location);
+        self.errors.extend(errors_after);
+    }
+
+    // With `max_token_len`, stop a value with escapes from growing while it is accumulated.
+    // It is cut just past the limit, so that `Tokenizer::next` still truncates it and reports it.
+    // Only cuts at a code point boundary of the input, where `bytes` is well-formed UTF-8.
+    #[inline]
+    fn limit_value_bytes(&self, bytes: &mut Vec<u8>) {
+        let max_len = match self.config.max_token_len {
+            Some(max_len) => max_len,
+            None => return,
+        };
+        if bytes.len() <= max_len.saturating_add(4) || !self.input.is_char_boundary(self.position) {
+            return;
+        }
+        let mut end = max_len + 1;
+        while bytes[end] & 0xC0 == 0x80 {
+            end += 1;
+        }
+        bytes.truncate(end);
+    }
+
+    // Copy the value consumed so far, before accumulating the rest of a value with escapes.
+    // With `max_token_len`, only copy up to just past the limit, like `limit_value_bytes`.
+    fn value_bytes_from(&self, start_pos: SourcePosition) -> Vec<u8> {
+        let value = self.slice_from(start_pos);
+        let end = match self.config.max_token_len {
+            Some(max_len) if value.len() > max_len.saturating_add(4) => {
+                let mut end = max_len + 1;
+                while !value.is_char_boundary(end) {
+                    end += 1;
+                }
+                end
+            }
+            _ => value.len(),
+        };
+        value.as_bytes()[..end].to_owned()
+    }
+
+    // Consume a U+0000 NULL and write its replacement.
+    fn consume_null_and_write(&mut self, bytes: &mut Vec<u8>) {
+        self.see_null();
+        self.advance(1);
+        let replacement = self.config.replacement_char;
+        bytes.extend(replacement.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    // Report a U+0000 NULL at the current position, if the configuration asks for it.
+    fn see_null(&mut self) {
+        if self.config.error_on_null {
+            let location = self.current_source_location();
+            self.report_error(TokenizerErrorKind::NullCharacter, location)
+        }
+    }
+
     #[inline]
     pub fn state(&self) -> ParserState {
         ParserState {
@@ -310,6 +913,16 @@ impl<'a> Tokenizer<'a> {
         &self.input[range.start.0..range.end.0]
     }
 
+    #[inline]
+    pub fn input(&self) -> &'a str {
+        self.input
+    }
+
+    #[inline]
+    pub fn remaining(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
     pub fn current_source_line(&self) -> &'a str {
         let current = self.position;
         let start = self.input[0..current]
@@ -321,6 +934,28 @@ impl<'a> Tokenizer<'a> {
         &self.input[start..end]
     }
 
+    pub fn current_column_with_tab_width(&self, tab_width: u32) -> u32 {
+        let tab_width = tab_width.max(1);
+        let current = self.position;
+        let start = match self.input[0..current].rfind(|c| matches!(c, '\r' | '\n' | '\x0C')) {
+            Some(start) => start + 1,
+            // Columns on the first line are counted from after a byte order mark.
+            None if self.config.skip_byte_order_mark && self.input.starts_with('\u{FEFF}') => {
+                '\u{FEFF}'.len_utf8()
+            }
+            None => 0,
+        };
+        let mut column = 0;
+        for c in self.input[start..current].chars() {
+            if c == '\t' {
+                column = (column / tab_width + 1) * tab_width;
+            } else {
+                column += c.len_utf16() as u32;
+            }
+        }
+        column + 1
+    }
+
     #[inline]
     pub fn next_byte(&self) -> Option<u8> {
         if self.is_eof() {
@@ -361,6 +996,18 @@ impl<'a> Tokenizer<'a> {
         self.position += n
     }
 
+    // Advance over a run of spaces and tabs in one step.
+    // They are single ASCII bytes and do not start a new line,
+    // so only the position needs to change.
+    #[inline]
+    fn consume_spaces_and_tabs(&mut self) {
+        let len = self.input.as_bytes()[self.position..]
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+        self.position += len
+    }
+
     // Assumes non-EOF
     #[inline]
     fn next_byte_unchecked(&self) -> u8 {
@@ -459,7 +1106,7 @@ impl<'a> Tokenizer<'a> {
         while !self.is_eof() {
             match_byte! { self.next_byte_unchecked(),
                 b' ' | b'\t' => {
-                    self.advance(1)
+                    self.consume_spaces_and_tabs()
                 },
                 b'\n' | b'\x0C' | b'\r' => {
                     self.consume_newline();
@@ -478,11 +1125,21 @@ impl<'a> Tokenizer<'a> {
         }
     }
 
+    pub fn consume_until_after(&mut self, delimiters: &[TokenKind]) {
+        while let Ok(token) = self.next() {
+            if delimiters.contains(&token.kind()) {
+                return;
+            } else if let Some(block_type) = token.block_start() {
+                consume_until_end_of_block(block_type, self);
+            }
+        }
+    }
+
     pub fn skip_cdc_and_cdo(&mut self) {
         while !self.is_eof() {
             match_byte! { self.next_byte_unchecked(),
                 b' ' | b'\t' => {
-                    self.advance(1)
+                    self.consume_spaces_and_tabs()
                 },
                 b'\n' | b'\x0C' | b'\r' => {
                     self.consume_newline();
@@ -516,6 +1173,101 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// An iterator over all the tokens of some CSS input, including whitespace and comments.
+///
+/// Unlike `Parser`, this does not treat blocks specially:
+/// the contents of blocks and their closing tokens are returned in source order.
+#[derive(Clone)]
+pub struct Tokens<'a> {
+    tokenizer: Tokenizer<'a>,
+}
+
+impl<'a> Tokens<'a> {
+    /// Start tokenizing the given input.
+    #[inline]
+    pub fn new(input: &'a str) -> Tokens<'a> {
+        Tokens {
+            tokenizer: Tokenizer::new(input),
+        }
+    }
+
+    /// Start tokenizing the given input with non-default options.
+    #[inline]
+    pub fn with_config(input: &'a str, config: TokenizerConfig) -> Tokens<'a> {
+        Tokens {
+            tokenizer: Tokenizer::with_config(input, 0, config),
+        }
+    }
+
+    /// Return the position after the last token returned.
+    #[inline]
+    pub fn position(&self) -> SourcePosition {
+        self.tokenizer.position()
+    }
+
+    /// Advance past any whitespace and comments,
+    /// so that the next token returned is neither.
+    /// Does nothing if the next token is already something else.
+    #[inline]
+    pub fn skip_whitespace(&mut self) {
+        self.tokenizer.skip_whitespace()
+    }
+
+    /// Discard tokens until one of the given kinds is found outside of any nested block,
+    /// or until the end of the input.
+    /// The token found is discarded too, so the next token returned is the one after it.
+    ///
+    /// This is how a parser recovers from a bad declaration by skipping to the next `;`:
+    /// a `;` inside parentheses, square brackets or curly brackets does not stop it.
+    pub fn consume_until_after(&mut self, delimiters: &[TokenKind]) {
+        self.tokenizer.consume_until_after(delimiters)
+    }
+
+    /// Return the whole input, as given to `new`.
+    #[inline]
+    pub fn input(&self) -> &'a str {
+        self.tokenizer.input()
+    }
+
+    /// Return the part of the input after the last token returned,
+    /// for example to report where tokenizing stopped or to hand the rest to another parser.
+    #[inline]
+    pub fn remaining(&self) -> &'a str {
+        self.tokenizer.remaining()
+    }
+
+    /// Return the line and column of the position after the last token returned.
+    #[inline]
+    pub fn current_source_location(&self) -> SourceLocation {
+        self.tokenizer.current_source_location()
+    }
+
+    /// Return the column of the position after the last token returned,
+    /// as an editor would display it:
+    /// a tab advances to the next multiple of `tab_width` columns.
+    ///
+    /// With a `tab_width` of 1 (or 0), this is the column of `current_source_location`.
+    #[inline]
+    pub fn current_column_with_tab_width(&self, tab_width: u32) -> u32 {
+        self.tokenizer.current_column_with_tab_width(tab_width)
+    }
+
+    /// Return the recoverable errors found in the tokens returned so far.
+    #[inline]
+    pub fn errors(&self) -> &[TokenizerError] {
+        self.tokenizer.errors()
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token<'a>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Token<'a>> {
+        self.tokenizer.next().ok()
+    }
+}
+
 /// A position from the start of the input, counted in UTF-8 bytes.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct SourcePosition(pub(crate) usize);
@@ -539,6 +1291,351 @@ pub struct SourceLocation {
     pub column: u32,
 }
 
+/// A recoverable error found while tokenizing.
+///
+/// The tokenizer still returns tokens as the specification requires,
+/// for example the contents of a string that is not closed before the end of the input.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct TokenizerError {
+    /// What kind of error this is.
+    pub kind: TokenizerErrorKind,
+
+    /// Where the error is: the start of an unterminated string or comment,
+    /// the start of a token that was too long,
+    /// or otherwise the offending character.
+    pub location: SourceLocation,
+}
+
+/// The kinds of `TokenizerError`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum TokenizerErrorKind {
+    /// A quoted string reached the end of the input before its closing quote.
+    UnterminatedString,
+
+    /// A `/*` comment reached the end of the input before `*/`.
+    UnterminatedComment,
+
+    /// A U+0000 NULL character, literal or escaped, was replaced.
+    /// Only reported when `TokenizerConfig::error_on_null` is set.
+    NullCharacter,
+
+    /// A quoted string was ended by an unescaped newline, giving a `BadString` token.
+    BadString,
+
+    /// An unquoted URL contained a character not allowed there, giving a `BadUrl` token.
+    BadUrl,
+
+    /// A backslash was followed by a newline outside of a quoted string,
+    /// or by the end of the input.
+    InvalidEscape,
+
+    /// The value of a token was longer than `TokenizerConfig::max_token_len`, and was truncated.
+    TokenTooLong,
+}
+
+/// Options for tokenizing that differ from the specification’s defaults.
+///
+/// Use `TokenizerConfig::default()` and override fields as needed.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct TokenizerConfig {
+    /// The character that replaces U+0000 NULL, literal or escaped,
+    /// in names, strings and URLs. U+FFFD REPLACEMENT CHARACTER by default.
+    ///
+    /// Escapes of surrogates or of values above U+10FFFF
+    /// are still replaced with U+FFFD.
+    pub replacement_char: char,
+
+    /// Whether to report each U+0000 NULL as a `TokenizerErrorKind::NullCharacter` error.
+    /// The character is still replaced, so no token is lost.
+    pub error_on_null: bool,
+
+    /// The maximum length in bytes of the value of a token:
+    /// the name of an identifier, function, at-keyword, hash or dimension unit,
+    /// or the contents of a string or URL.
+    /// A longer value is truncated to the last code point boundary within the limit,
+    /// and reported as a `TokenizerErrorKind::TokenTooLong` error.
+    /// The rest of the token is still consumed, so the tokens that follow are unchanged.
+    ///
+    /// Values are only copied when truncated or when they contain escapes,
+    /// otherwise they borrow from the input.
+    /// A value with escapes stops growing past the limit while it is consumed.
+    /// No limit by default.
+    pub max_token_len: Option<usize>,
+
+    /// Whether to lowercase, in the ASCII range, the names of identifiers, functions,
+    /// at-keywords and dimension units, which are mostly compared case-insensitively.
+    /// Hashes, strings, URLs and custom property names such as `--Main` keep their case.
+    /// False by default.
+    pub lowercase_identifiers: bool,
+
+    /// Whether to skip a U+FEFF BYTE ORDER MARK at the very start of the input,
+    /// as the input of a whole stylesheet should be.
+    /// Columns on the first line are then counted from after it.
+    /// True by default.
+    ///
+    /// Before this option was added, a leading U+FEFF always started an identifier.
+    /// Set it to false when tokenizing part of a stylesheet,
+    /// where a U+FEFF at the start is a name code point like anywhere else.
+    pub skip_byte_order_mark: bool,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        TokenizerConfig {
+            replacement_char: '\u{FFFD}',
+            error_on_null: false,
+            max_token_len: None,
+            lowercase_identifiers: false,
+            skip_byte_order_mark: true,
+        }
+    }
+}
+
+/// Lowercase the name of an identifier, function, at-keyword or dimension unit
+/// in the ASCII range, unless it is a custom property name.
+/// Only allocates if the name has ASCII uppercase letters.
+fn lowercase_identifier(token: &mut Token) {
+    let name = match *token {
+        Ident(ref mut name)
+        | AtKeyword(ref mut name)
+        | Function(ref mut name)
+        | Dimension {
+            unit: ref mut name, ..
+        } => name,
+        _ => return,
+    };
+    if !name.starts_with("--") && name.bytes().any(|b| b.is_ascii_uppercase()) {
+        *name = name.to_ascii_lowercase().into();
+    }
+}
+
+/// Truncate the value of a token to at most `max_len` bytes,
+/// and return whether it was longer.
+fn truncate_token_value(token: &mut Token, max_len: usize) -> bool {
+    let value = match *token {
+        Ident(ref mut value)
+        | AtKeyword(ref mut value)
+        | Hash(ref mut value)
+        | IDHash(ref mut value)
+        | QuotedString(ref mut value)
+        | UnquotedUrl(ref mut value)
+        | Function(ref mut value)
+        | BadUrl(ref mut value)
+        | BadString(ref mut value)
+        | Dimension {
+            unit: ref mut value,
+            ..
+        } => value,
+        _ => return false,
+    };
+    if value.len() <= max_len {
+        return false;
+    }
+    let mut end = max_len;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    *value = value[..end].to_owned().into();
+    true
+}
+
+/// Tokenize the whole input, including whitespace and comments.
+///
+/// ```rust
+/// use cssparser::{tokenize_all, Token};
+///
+/// assert_eq!(
+///     tokenize_all("a b"),
+///     [Token::Ident("a".into()), Token::WhiteSpace(" "), Token::Ident("b".into())]
+/// );
+/// ```
+pub fn tokenize_all<'a>(input: &'a str) -> Vec<Token<'a>> {
+    Tokens::new(input).collect()
+}
+
+/// Tokenize the whole input, including whitespace and comments,
+/// and group tokens by the line (counted from 0) where they start.
+///
+/// The result has one entry per line of the input,
+/// which is empty for lines where no token starts.
+pub fn tokens_by_line<'a>(input: &'a str) -> Vec<Vec<Token<'a>>> {
+    let mut tokens = Tokens::new(input);
+    let mut lines = vec![vec![]];
+    loop {
+        let line = tokens.current_source_location().line as usize;
+        let token = match tokens.next() {
+            Some(token) => token,
+            None => break,
+        };
+        lines.resize(line + 1, vec![]);
+        lines[line].push(token);
+    }
+    let last_line = tokens.current_source_location().line as usize;
+    lines.resize(last_line + 1, vec![]);
+    lines
+}
+
+/// Return whether two inputs tokenize to the same sequence of tokens.
+///
+/// If `ignore_whitespace_and_comments` is true, comments are skipped,
+/// whitespace at the start and end is ignored,
+/// and the contents of whitespace between tokens is not compared.
+/// Whether there is whitespace between two tokens still matters.
+pub fn token_streams_equal(a: &str, b: &str, ignore_whitespace_and_comments: bool) -> bool {
+    if ignore_whitespace_and_comments {
+        significant_tokens(a) == significant_tokens(b)
+    } else {
+        Tokens::new(a).eq(Tokens::new(b))
+    }
+}
+
+fn significant_tokens<'a>(input: &'a str) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut pending_whitespace = false;
+    for token in Tokens::new(input) {
+        match token {
+            Token::Comment(_) => {}
+            Token::WhiteSpace(_) => pending_whitespace = !tokens.is_empty(),
+            token => {
+                if pending_whitespace {
+                    tokens.push(Token::WhiteSpace(" "));
+                    pending_whitespace = false;
+                }
+                tokens.push(token)
+            }
+        }
+    }
+    tokens
+}
+
+/// Remove the comments from CSS source, keeping all other tokens as written.
+///
+/// A comment between two tokens that would otherwise merge or change meaning,
+/// such as `a/**/b`, `1/**/px` or `<!/**/--`, is replaced with a space.
+/// This adds a whitespace token, which can matter in some contexts like selectors.
+pub fn strip_comments(input: &str) -> String {
+    let mut tokens = Tokens::new(input);
+    let mut output = String::with_capacity(input.len());
+    // Keep a leading byte order mark.
+    output.push_str(&input[..tokens.position().0]);
+    let mut previous = TokenSerializationType::nothing();
+    // The last token kept and its source, unless it is whitespace.
+    let mut previous_token = None;
+    let mut after_comment = false;
+    loop {
+        let start = tokens.position();
+        let token = match tokens.next() {
+            Some(token) => token,
+            None => break,
+        };
+        if let Comment(_) = token {
+            after_comment = true;
+            continue;
+        }
+        let source = &input[start.0..tokens.position().0];
+        let is_whitespace = matches!(token, WhiteSpace(_));
+        let serialization_type = token.serialization_type();
+        if after_comment {
+            let mut spaces = previous.needs_separator_when_before(serialization_type) as usize;
+            // The serialization rules do not cover everything that can be written in source,
+            // like a hex escape that would take a single space as its end,
+            // or `<!--` made of several tokens.
+            // A hex escape takes at most one space as its end, so two spaces always separate.
+            if let Some((ref previous_token, previous_source)) = previous_token {
+                while spaces < 2
+                    && !are_separate_tokens(previous_token, previous_source, spaces, source)
+                {
+                    spaces += 1;
+                }
+            }
+            // A U+FEFF that would be left at the start is not a byte order mark either.
+            let rest = &input[start.0..];
+            if spaces == 0
+                && (forms_cdo_or_cdc(&output, rest)
+                    || (output.is_empty() && source.starts_with('\u{FEFF}')))
+            {
+                spaces = 1;
+            }
+            output.push_str(&" ".repeat(spaces));
+        }
+        after_comment = false;
+        output.push_str(source);
+        previous = serialization_type;
+        previous_token = if is_whitespace {
+            None
+        } else {
+            Some((token, source))
+        };
+    }
+    output
+}
+
+// Return whether the sources of two tokens, with the given number of spaces between them,
+// still tokenize as the same two tokens, or as the same token followed by whitespace.
+// The first token, read from `before`, may take a space as the end of a hex escape.
+fn are_separate_tokens(before_token: &Token, before: &str, spaces: usize, after: &str) -> bool {
+    let joined = [before, &" ".repeat(spaces), after].concat();
+    // `before` is not the start of a stylesheet, so a U+FEFF there is part of the token.
+    let config = TokenizerConfig {
+        skip_byte_order_mark: false,
+        ..TokenizerConfig::default()
+    };
+    let mut tokens = Tokens::with_config(&joined, config);
+    if tokens.next().as_ref() != Some(before_token) || tokens.position().0 > before.len() + spaces {
+        return false;
+    }
+    let after_is_whitespace = after.starts_with(&[' ', '\t', '\n', '\r', '\x0C'][..]);
+    if after_is_whitespace {
+        return true;
+    }
+    tokens.skip_whitespace();
+    tokens.position().0 == before.len() + spaces
+}
+
+// Return whether `<!--` or `-->` would be formed across the end of `before`
+// and the start of `after`.
+fn forms_cdo_or_cdc(before: &str, after: &str) -> bool {
+    ["<!--", "-->"].iter().any(|delimiter| {
+        (1..delimiter.len())
+            .any(|i| before.ends_with(&delimiter[..i]) && after.starts_with(&delimiter[i..]))
+    })
+}
+
+/// Apply the input preprocessing step of CSS Syntax:
+/// replace CRLF pairs, lone CR and form feed with LF,
+/// and U+0000 NULL with U+FFFD REPLACEMENT CHARACTER.
+///
+/// https://drafts.csswg.org/css-syntax/#input-preprocessing
+///
+/// The tokenizer already gives these the same meaning without preprocessing,
+/// but the source text of tokens (for example whitespace or `Parser::slice`) keeps them as-is.
+/// Surrogates cannot occur in a `&str`, so they need no handling here.
+/// The input is borrowed unchanged when there is nothing to replace.
+pub fn preprocess<'a>(input: &'a str) -> Cow<'a, str> {
+    if !input
+        .bytes()
+        .any(|b| b == b'\r' || b == b'\x0C' || b == b'\0')
+    {
+        return Cow::Borrowed(input);
+    }
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                output.push('\n')
+            }
+            '\x0C' => output.push('\n'),
+            '\0' => output.push('\u{FFFD}'),
+            c => output.push(c),
+        }
+    }
+    Cow::Owned(output)
+}
+
 fn next_token<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>, ()> {
     if tokenizer.is_eof() {
         return Err(());
@@ -648,7 +1745,12 @@ fn next_token<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>, ()> {
         b'[' => { tokenizer.advance(1); SquareBracketBlock },
         b'\\' => {
             if !tokenizer.has_newline_at(1) { consume_ident_like(tokenizer) }
-            else { tokenizer.advance(1); Delim('\\') }
+            else {
+                let location = tokenizer.current_source_location();
+                tokenizer.report_error(TokenizerErrorKind::InvalidEscape, location);
+                tokenizer.advance(1);
+                Delim('\\')
+            }
         },
         b']' => { tokenizer.advance(1); CloseSquareBracket },
         b'^' => {
@@ -682,13 +1784,13 @@ fn consume_whitespace<'a>(tokenizer: &mut Tokenizer<'a>, newline: bool) -> Token
     if newline {
         tokenizer.consume_newline();
     } else {
-        tokenizer.advance(1);
+        tokenizer.consume_spaces_and_tabs();
     }
     while !tokenizer.is_eof() {
         let b = tokenizer.next_byte_unchecked();
         match_byte! { b,
             b' ' | b'\t' => {
-                tokenizer.advance(1);
+                tokenizer.consume_spaces_and_tabs();
             }
             b'\n' | b'\x0C' | b'\r' => {
                 tokenizer.consume_newline();
@@ -728,6 +1830,7 @@ fn check_for_source_map<'a>(tokenizer: &mut Tokenizer<'a>, contents: &'a str) {
 }
 
 fn consume_comment<'a>(tokenizer: &mut Tokenizer<'a>) -> &'a str {
+    let location = tokenizer.current_source_location();
     tokenizer.advance(2); // consume "/*"
     let start_position = tokenizer.position();
     while !tokenizer.is_eof() {
@@ -753,6 +1856,7 @@ fn consume_comment<'a>(tokenizer: &mut Tokenizer<'a>) -> &'a str {
             }
         }
     }
+    tokenizer.report_error(TokenizerErrorKind::UnterminatedComment, location);
     let contents = tokenizer.slice_from(start_position);
     check_for_source_map(tokenizer, contents);
     contents
@@ -770,12 +1874,15 @@ fn consume_quoted_string<'a>(
     tokenizer: &mut Tokenizer<'a>,
     single_quote: bool,
 ) -> Result<CowRcStr<'a>, CowRcStr<'a>> {
+    let location = tokenizer.current_source_location();
+    let error_count = tokenizer.errors.len();
     tokenizer.advance(1); // Skip the initial quote
                           // start_pos is at code point boundary, after " or '
     let start_pos = tokenizer.position();
     let mut string_bytes;
     loop {
         if tokenizer.is_eof() {
+            tokenizer.report_error(TokenizerErrorKind::UnterminatedString, location);
             return Ok(tokenizer.slice_from(start_pos).into());
         }
         match_byte! { tokenizer.next_byte_unchecked(),
@@ -801,10 +1908,12 @@ fn consume_quoted_string<'a>(
                 // * so is the current position (which is before '\\' or '\0'
                 //
                 // So `string_bytes` is well-formed UTF-8.
-                string_bytes = tokenizer.slice_from(start_pos).as_bytes().to_owned();
+                string_bytes = tokenizer.value_bytes_from(start_pos);
                 break
             }
             b'\n' | b'\r' | b'\x0C' => {
+                let newline = tokenizer.current_source_location();
+                tokenizer.report_error(TokenizerErrorKind::BadString, newline);
                 return Err(tokenizer.slice_from(start_pos).into())
             },
             b'\x80'..=b'\xBF' => { tokenizer.consume_continuation_byte(); }
@@ -816,10 +1925,21 @@ fn consume_quoted_string<'a>(
         }
     }
 
-    while !tokenizer.is_eof() {
+    loop {
+        tokenizer.limit_value_bytes(&mut string_bytes);
+        if tokenizer.is_eof() {
+            tokenizer.report_error_before(
+                error_count,
+                TokenizerErrorKind::UnterminatedString,
+                location,
+            );
+            break;
+        }
         let b = tokenizer.next_byte_unchecked();
         match_byte! { b,
             b'\n' | b'\r' | b'\x0C' => {
+                let newline = tokenizer.current_source_location();
+                tokenizer.report_error(TokenizerErrorKind::BadString, newline);
                 return Err(
                     // string_bytes is well-formed UTF-8, see other comments.
                     unsafe {
@@ -855,8 +1975,7 @@ fn consume_quoted_string<'a>(
                 continue;
             }
             b'\0' => {
-                tokenizer.advance(1);
-                string_bytes.extend("\u{FFFD}".as_bytes());
+                tokenizer.consume_null_and_write(&mut string_bytes);
                 continue;
             }
             b'\x80'..=b'\xBF' => { tokenizer.consume_continuation_byte(); }
@@ -928,7 +2047,7 @@ fn consume_name<'a>(tokenizer: &mut Tokenizer<'a>) -> CowRcStr<'a> {
                 // * so is the current position (which is before '\\' or '\0'
                 //
                 // So `value_bytes` is well-formed UTF-8.
-                value_bytes = tokenizer.slice_from(start_pos).as_bytes().to_owned();
+                value_bytes = tokenizer.value_bytes_from(start_pos);
                 break
             }
             b'\x80'..=b'\xBF' => { tokenizer.consume_continuation_byte(); }
@@ -941,6 +2060,7 @@ fn consume_name<'a>(tokenizer: &mut Tokenizer<'a>) -> CowRcStr<'a> {
     }
 
     while !tokenizer.is_eof() {
+        tokenizer.limit_value_bytes(&mut value_bytes);
         let b = tokenizer.next_byte_unchecked();
         match_byte! { b,
             b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-'  => {
@@ -954,8 +2074,7 @@ fn consume_name<'a>(tokenizer: &mut Tokenizer<'a>) -> CowRcStr<'a> {
                 consume_escape_and_write(tokenizer, &mut value_bytes)
             }
             b'\0' => {
-                tokenizer.advance(1);
-                value_bytes.extend("\u{FFFD}".as_bytes());
+                tokenizer.consume_null_and_write(&mut value_bytes);
             },
             b'\x80'..=b'\xBF' => {
                 // This byte *is* part of a multi-byte code point,
@@ -1002,6 +2121,26 @@ fn byte_to_decimal_digit(b: u8) -> Option<u32> {
     }
 }
 
+/// Convert an integer to `f32`, keeping it in the range of `i32` if and only if it was.
+///
+/// The closest `f32` can be on the other side of the ends of that range,
+/// as 2^31 is for 2147483647 and -2^31 is for -2147483649.
+/// `Token::as_i32` relies on this to tell integers that fit from those `int_value` was clamped for.
+fn integer_to_f32(value: f64) -> f32 {
+    let rounded = value as f32;
+    let fits = value >= i32::MIN as f64 && value <= i32::MAX as f64;
+    let rounded_fits = rounded as f64 >= i32::MIN as f64 && rounded as f64 <= i32::MAX as f64;
+    if fits == rounded_fits {
+        rounded
+    } else if fits {
+        // One step towards zero.
+        f32::from_bits(rounded.to_bits() - 1)
+    } else {
+        // One step away from zero.
+        f32::from_bits(rounded.to_bits() + 1)
+    }
+}
+
 fn consume_numeric<'a>(tokenizer: &mut Tokenizer<'a>) -> Token<'a> {
     // Parse [+-]?\d*(\.\d+)?([eE][+-]?\d+)?
     // But this is always called so that there is at least one digit in \d*(\.\d+)?
@@ -1097,7 +2236,11 @@ fn consume_numeric<'a>(tokenizer: &mut Tokenizer<'a>) -> Token<'a> {
             has_sign: has_sign,
         };
     }
-    let value = value as f32;
+    let value = if is_integer {
+        integer_to_f32(value)
+    } else {
+        value as f32
+    };
     if is_ident_start(tokenizer) {
         let unit = consume_name(tokenizer);
         Dimension {
@@ -1204,7 +2347,6 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
                 }
                 b'\x01'..=b'\x08' | b'\x0B' | b'\x0E'..=b'\x1F' | b'\x7F'  // non-printable
                     | b'"' | b'\'' | b'(' => {
-                    tokenizer.advance(1);
                     return consume_bad_url(tokenizer, start_pos)
                 },
                 b'\\' | b'\0' => {
@@ -1213,7 +2355,7 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
                     // * so is the current position (which is before '\\' or '\0'
                     //
                     // So `string_bytes` is well-formed UTF-8.
-                    string_bytes = tokenizer.slice_from(start_pos).as_bytes().to_owned();
+                    string_bytes = tokenizer.value_bytes_from(start_pos);
                     break
                 }
                 b'\x80'..=b'\xBF' => { tokenizer.consume_continuation_byte(); }
@@ -1225,6 +2367,7 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
             }
         }
         while !tokenizer.is_eof() {
+            tokenizer.limit_value_bytes(&mut string_bytes);
             let b = tokenizer.next_byte_unchecked();
             match_byte! { b,
                 b' ' | b'\t' | b'\n' | b'\r' | b'\x0C' => {
@@ -1238,21 +2381,19 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
                 }
                 b'\x01'..=b'\x08' | b'\x0B' | b'\x0E'..=b'\x1F' | b'\x7F'  // non-printable
                     | b'"' | b'\'' | b'(' => {
-                    tokenizer.advance(1);
                     return consume_bad_url(tokenizer, start_pos);
                 }
                 b'\\' => {
-                    tokenizer.advance(1);
-                    if tokenizer.has_newline_at(0) {
+                    if tokenizer.has_newline_at(1) {
                         return consume_bad_url(tokenizer, start_pos)
                     }
+                    tokenizer.advance(1);
 
                     // This pushes one well-formed code point to string_bytes
                     consume_escape_and_write(tokenizer, &mut string_bytes)
                 },
                 b'\0' => {
-                    tokenizer.advance(1);
-                    string_bytes.extend("\u{FFFD}".as_bytes());
+                    tokenizer.consume_null_and_write(&mut string_bytes);
                 }
                 b'\x80'..=b'\xBF' => {
                     // We’ll end up copying the whole code point
@@ -1296,8 +2437,7 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
                 b'\n' | b'\x0C' | b'\r' => {
                     tokenizer.consume_newline();
                 }
-                b => {
-                    tokenizer.consume_known_byte(b);
+                _ => {
                     return consume_bad_url(tokenizer, start_pos);
                 }
             }
@@ -1306,7 +2446,11 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
     }
 
     fn consume_bad_url<'a>(tokenizer: &mut Tokenizer<'a>, start_pos: SourcePosition) -> Token<'a> {
-        // Consume up to the closing )
+        let location = tokenizer.current_source_location();
+        tokenizer.report_error(TokenizerErrorKind::BadUrl, location);
+        // Consume up to the closing ), as in
+        // https://drafts.csswg.org/css-syntax/#consume-remnants-of-bad-url
+        // Nested ( are not matched: the first unescaped ) ends the URL.
         while !tokenizer.is_eof() {
             match_byte! { tokenizer.next_byte_unchecked(),
                 b')' => {
@@ -1316,8 +2460,10 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
                 }
                 b'\\' => {
                     tokenizer.advance(1);
-                    if matches!(tokenizer.next_byte(), Some(b')') | Some(b'\\')) {
-                        tokenizer.advance(1); // Skip an escaped ')' or '\'
+                    // A backslash followed by a newline is not an escape,
+                    // and the newline is consumed on the next iteration.
+                    if !tokenizer.is_eof() && !tokenizer.has_newline_at(0) {
+                        tokenizer.consume_char(); // Skip the escaped code point
                     }
                 }
                 b'\n' | b'\x0C' | b'\r' => {
@@ -1332,23 +2478,6 @@ fn consume_unquoted_url<'a>(tokenizer: &mut Tokenizer<'a>) -> Result<Token<'a>,
     }
 }
 
-// (value, number of digits up to 6)
-fn consume_hex_digits<'a>(tokenizer: &mut Tokenizer<'a>) -> (u32, u32) {
-    let mut value = 0;
-    let mut digits = 0;
-    while digits < 6 && !tokenizer.is_eof() {
-        match byte_to_hex_digit(tokenizer.next_byte_unchecked()) {
-            Some(digit) => {
-                value = value * 16 + digit;
-                digits += 1;
-                tokenizer.advance(1);
-            }
-            None => break,
-        }
-    }
-    (value, digits)
-}
-
 // Same constraints as consume_escape except it writes into `bytes` the result
 // instead of returning it.
 fn consume_escape_and_write(tokenizer: &mut Tokenizer, bytes: &mut Vec<u8>) {
@@ -1363,35 +2492,158 @@ fn consume_escape_and_write(tokenizer: &mut Tokenizer, bytes: &mut Vec<u8>) {
 // and that the next input character has already been verified
 // to not be a newline.
 fn consume_escape(tokenizer: &mut Tokenizer) -> char {
-    if tokenizer.is_eof() {
-        return '\u{FFFD}';
-    } // Escaped EOF
-    match_byte! { tokenizer.next_byte_unchecked(),
-        b'0'..=b'9' | b'A'..=b'F' | b'a'..=b'f' => {
-            let (c, _) = consume_hex_digits(tokenizer);
-            if !tokenizer.is_eof() {
-                match_byte! { tokenizer.next_byte_unchecked(),
-                    b' ' | b'\t' => {
-                        tokenizer.advance(1)
-                    }
-                    b'\n' | b'\x0C' | b'\r' => {
-                        tokenizer.consume_newline();
+    let input = &tokenizer.input[tokenizer.position..];
+    if input.is_empty() {
+        // Report at the backslash, the byte before.
+        let location = tokenizer.current_source_location();
+        let backslash = SourceLocation {
+            column: location.column - 1,
+            ..location
+        };
+        tokenizer.report_error(TokenizerErrorKind::InvalidEscape, backslash);
+    }
+    let (mut c, len) = parse_escape(input);
+    // An escaped U+0000 NULL, either as a literal byte or in hex digits.
+    let escaped = input[..len].trim_end();
+    if escaped == "\0" || (!escaped.is_empty() && escaped.bytes().all(|b| b == b'0')) {
+        tokenizer.see_null();
+        c = tokenizer.config.replacement_char;
+    }
+    let end = tokenizer.position + len;
+    while tokenizer.position < end {
+        match_byte! { tokenizer.next_byte_unchecked(),
+            b'\n' | b'\x0C' | b'\r' => {
+                tokenizer.consume_newline();
+            }
+            _ => {
+                tokenizer.consume_char();
+            }
+        }
+    }
+    c
+}
+
+/// Return the number at the start of the source of a `Number`, `Percentage` or `Dimension` token,
+/// exactly as written: `+5`, `.5` and `5E2` are returned unchanged.
+///
+/// This is the source without the `%` of a percentage or the unit of a dimension.
+/// The source of a token can be obtained with
+/// `Parser::next_including_whitespace_and_comments_with_span` and `Parser::slice`.
+/// For other input, the result is the longest prefix that has the syntax of a number,
+/// which may be empty.
+pub fn numeric_repr(token_source: &str) -> &str {
+    // [+-]?\d*(\.\d+)?([eE][+-]?\d+)?
+    let bytes = token_source.as_bytes();
+    let is_digit = |i: usize| matches!(bytes.get(i), Some(&(b'0'..=b'9')));
+    let mut end = 0;
+    if matches!(bytes.first(), Some(&b'+') | Some(&b'-')) {
+        end += 1;
+    }
+    while is_digit(end) {
+        end += 1;
+    }
+    if bytes.get(end) == Some(&b'.') && is_digit(end + 1) {
+        end += 2;
+        while is_digit(end) {
+            end += 1;
+        }
+    }
+    let has_exponent_marker = matches!(bytes.get(end), Some(&b'e') | Some(&b'E'));
+    if has_exponent_marker {
+        let exponent_digits = if matches!(bytes.get(end + 1), Some(&b'+') | Some(&b'-')) {
+            end + 2
+        } else {
+            end + 1
+        };
+        if is_digit(exponent_digits) {
+            end = exponent_digits + 1;
+            while is_digit(end) {
+                end += 1;
+            }
+        }
+    }
+    &token_source[..end]
+}
+
+/// Decode the escape sequence at the start of `input`,
+/// which is the text that follows a U+005C REVERSE SOLIDUS (`\`).
+///
+/// https://drafts.csswg.org/css-syntax/#consume-escaped-code-point
+///
+/// Returns the escaped character and the number of bytes of `input` it spans.
+/// A hex escape is one to six hex digits, followed by one optional whitespace character
+/// (with `\r\n` counting as one).
+/// It decodes to U+FFFD REPLACEMENT CHARACTER for zero, a surrogate,
+/// or a value above U+10FFFF.
+/// An escaped U+0000 NULL or an empty `input` also decodes to U+FFFD.
+/// Any other character escapes itself.
+///
+/// The tokenizer only calls this when `input` does not start with a newline,
+/// since a backslash followed by a newline is not a valid escape.
+pub fn parse_escape(input: &str) -> (char, usize) {
+    let bytes = input.as_bytes();
+    match bytes.first() {
+        None => ('\u{FFFD}', 0), // Escaped EOF
+        Some(&b'\0') => ('\u{FFFD}', 1),
+        Some(&b) if byte_to_hex_digit(b).is_some() => {
+            let mut value = 0;
+            let mut len = 0;
+            while len < 6 {
+                match bytes.get(len).cloned().and_then(byte_to_hex_digit) {
+                    Some(digit) => {
+                        value = value * 16 + digit;
+                        len += 1;
                     }
-                    _ => {}
+                    None => break,
                 }
             }
-            static REPLACEMENT_CHAR: char = '\u{FFFD}';
-            if c != 0 {
-                let c = char::from_u32(c);
-                c.unwrap_or(REPLACEMENT_CHAR)
-            } else {
-                REPLACEMENT_CHAR
+            match bytes.get(len) {
+                Some(&b' ') | Some(&b'\t') | Some(&b'\n') | Some(&b'\x0C') => len += 1,
+                Some(&b'\r') => {
+                    len += if bytes.get(len + 1) == Some(&b'\n') {
+                        2
+                    } else {
+                        1
+                    };
+                }
+                _ => {}
             }
-        },
-        b'\0' => {
-            tokenizer.advance(1);
-            '\u{FFFD}'
+            // Six hex digits are at most 0xFFFFFF, so every other value is a valid char.
+            let c = match value {
+                0 | 0xD800..=0xDFFF | 0x11_0000..=0xFF_FFFF => '\u{FFFD}',
+                _ => char::from_u32(value).unwrap(),
+            };
+            (c, len)
+        }
+        Some(_) => {
+            let c = input.chars().next().unwrap();
+            (c, c.len_utf8())
+        }
+    }
+}
+
+/// Decode the escapes of `input` as they would be in an identifier,
+/// with `parse_escape`.
+///
+/// A backslash followed by a newline is not an escape and is kept as is.
+/// A backslash at the end of `input` decodes to U+FFFD REPLACEMENT CHARACTER.
+///
+/// This is the inverse of `serialize_identifier` and `serialize_string`
+/// for the text they write between quotes.
+pub fn unescape(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(backslash) = rest.find('\\') {
+        result.push_str(&rest[..backslash]);
+        rest = &rest[backslash + 1..];
+        if rest.starts_with(|c| matches!(c, '\n' | '\r' | '\x0C')) {
+            result.push('\\');
+            continue;
         }
-        _ => { tokenizer.consume_char() }
+        let (c, len) = parse_escape(rest);
+        result.push(c);
+        rest = &rest[len..];
     }
+    result.push_str(rest);
+    result
 }
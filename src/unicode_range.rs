@@ -5,13 +5,17 @@
 //! https://drafts.csswg.org/css-syntax/#urange
 
 use std::char;
+use std::cmp;
 use std::fmt;
+use std::ops::RangeInclusive;
 use tokenizer::Token;
 use {BasicParseError, Parser, ToCss};
 
 /// One contiguous range of code points.
 ///
 /// Can not be empty. Can represent a single code point when start == end.
+/// Like in the `unicode-range` descriptor of `@font-face`,
+/// the range may include surrogate code points, which no character matches.
 #[derive(PartialEq, Eq, Clone, Hash)]
 #[repr(C)]
 pub struct UnicodeRange {
@@ -24,6 +28,9 @@ pub struct UnicodeRange {
 
 impl UnicodeRange {
     /// https://drafts.csswg.org/css-syntax/#urange-syntax
+    ///
+    /// An end above U+10FFFF is clamped to U+10FFFF.
+    /// A range whose start is after its end (after clamping) is rejected.
     pub fn parse<'i, 't>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
         // <urange> =
         //   u '+' <ident-token> '?'* |
@@ -49,12 +56,30 @@ impl UnicodeRange {
                     .new_basic_unexpected_token_error(Token::Ident(concatenated_tokens.into())))
             }
         };
-        if range.end > char::MAX as u32 || range.start > range.end {
+        // Like in the `unicode-range` descriptor of `@font-face`,
+        // clamp the end to the maximum code point rather than rejecting the whole range.
+        // A range that would still be empty is a syntax error.
+        let range = UnicodeRange {
+            start: range.start,
+            end: cmp::min(range.end, char::MAX as u32),
+        };
+        if range.start > range.end {
             Err(input.new_basic_unexpected_token_error(Token::Ident(concatenated_tokens.into())))
         } else {
             Ok(range)
         }
     }
+
+    /// Return whether the given character is in this range.
+    pub fn contains(&self, c: char) -> bool {
+        self.start <= c as u32 && c as u32 <= self.end
+    }
+
+    /// Return this range as a range of characters,
+    /// or `None` if its start or end is a surrogate code point or above U+10FFFF.
+    pub fn to_char_range(&self) -> Option<RangeInclusive<char>> {
+        Some(char::from_u32(self.start)?..=char::from_u32(self.end)?)
+    }
 }
 
 fn parse_tokens<'i, 't>(input: &mut Parser<'i, 't>) -> Result<(), BasicParseError<'i>> {
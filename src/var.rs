@@ -0,0 +1,61 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+ * License, v. 2.0. If a copy of the MPL was not distributed with this
+ * file, You can obtain one at http://mozilla.org/MPL/2.0/. */
+
+//! https://drafts.csswg.org/css-variables/#using-variables
+
+use tokenizer::Token;
+use {BasicParseError, ComponentValue, CowRcStr, ParseError, Parser};
+
+/// A parsed `var()` function, such as `var(--main-color, blue)`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VarFunction<'i> {
+    /// The name of the custom property, including its leading `--`.
+    pub name: CowRcStr<'i>,
+
+    /// The component values of the fallback after the comma, if any,
+    /// without surrounding whitespace.
+    /// This is empty for `var(--x,)`, whose fallback is empty but present.
+    pub fallback: Option<Vec<ComponentValue<'i>>>,
+}
+
+impl<'i> VarFunction<'i> {
+    /// Parse a `var()` function, starting at its function token.
+    pub fn parse<'t>(input: &mut Parser<'i, 't>) -> Result<Self, BasicParseError<'i>> {
+        input.expect_function_matching("var")?;
+        input
+            .parse_nested_block(|input| parse_arguments(input).map_err(ParseError::from))
+            .map_err(ParseError::<()>::basic)
+    }
+}
+
+fn parse_arguments<'i, 't>(
+    input: &mut Parser<'i, 't>,
+) -> Result<VarFunction<'i>, BasicParseError<'i>> {
+    let location = input.current_source_location();
+    let name = input.expect_ident_cloned()?;
+    if !name.starts_with("--") {
+        return Err(location.new_basic_unexpected_token_error(Token::Ident(name)));
+    }
+    match input.next() {
+        Err(_) => Ok(VarFunction {
+            name,
+            fallback: None,
+        }),
+        Ok(&Token::Comma) => {
+            input.skip_whitespace();
+            let mut fallback = ComponentValue::parse_list(input);
+            while let Some(&ComponentValue::Token(Token::WhiteSpace(_))) = fallback.last() {
+                fallback.pop();
+            }
+            Ok(VarFunction {
+                name,
+                fallback: Some(fallback),
+            })
+        }
+        Ok(t) => {
+            let t = t.clone();
+            Err(input.new_basic_unexpected_token_error(t))
+        }
+    }
+}